@@ -0,0 +1,201 @@
+//! A small client for the poker server's JSON protocol
+//!
+//! `Client` connects to the server's `/ws` endpoint, asks for the JSON protocol in the WebSocket handshake and then sends typed requests and reads typed events. The requests and events come from the `poker-protocol` crate, the same types the server uses, so a client built on this crate cannot drift from the server's wire format.
+//!
+//! ```no_run
+//! use poker_client::{Client, Event, Request};
+//!
+//! # async fn play() -> Result<(), poker_client::ClientError> {
+//! let mut client = Client::connect("ws://localhost:1112/ws").await?;
+//! client.send(&Request::Login { username: "alice".to_string(), password: "hunter2".to_string() }).await?;
+//! while let Some(event) = client.next_event().await {
+//!     if let Event::HandDealt { cards } = event? {
+//!         println!("Dealt {} cards", cards.len());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use futures_util::{SinkExt, StreamExt};
+pub use poker_protocol::card::Card;
+pub use poker_protocol::{ActionKind, Event, LeaderboardEntry, LeaderboardMetric, PlayerStats, PotResult, Request, VariantStats, VersionedEvent};
+use poker_protocol::{negotiate, Protocol, SUBPROTOCOL_PREFIX, SUPPORTED_VERSIONS};
+use std::fmt;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::error::ProtocolError;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+/// The error returned when talking to the server fails.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The connection could not be opened or was lost.
+    WebSocket(tungstenite::Error),
+    /// The server sent a message that is not a valid event.
+    Json(serde_json::Error),
+    /// The server did not agree to any JSON protocol version the client speaks.
+    NotNegotiated,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::WebSocket(e) => write!(f, "websocket error: {}", e),
+            ClientError::Json(e) => write!(f, "invalid event: {}", e),
+            ClientError::NotNegotiated => write!(f, "the server did not accept the JSON protocol"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<tungstenite::Error> for ClientError {
+    fn from(e: tungstenite::Error) -> Self {
+        ClientError::WebSocket(e)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(e: serde_json::Error) -> Self {
+        ClientError::Json(e)
+    }
+}
+
+/// A JSON connection to the poker server.
+pub struct Client {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    version: u32,
+}
+
+impl Client {
+    /// Connects to the server and negotiates the JSON protocol.
+    ///
+    /// # Arguments
+    /// * `url` - The server's WebSocket endpoint, such as `ws://localhost:1112/ws`.
+    ///
+    /// # Returns
+    /// * `Ok(Client)` - A connection speaking the highest protocol version both sides support.
+    /// * `Err(ClientError)` - If the connection failed or the server would not speak JSON.
+    pub async fn connect(url: &str) -> Result<Client, ClientError> {
+        let mut request = url.into_client_request()?;
+        // offer every version, newest first
+        let offered = SUPPORTED_VERSIONS
+            .iter()
+            .rev()
+            .map(|version| format!("{}{}", SUBPROTOCOL_PREFIX, version))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let offered = HeaderValue::from_str(&offered).expect("subprotocol names are valid header values");
+        request.headers_mut().insert("sec-websocket-protocol", offered);
+
+        // a server that only speaks text answers without a subprotocol, which fails the handshake
+        let (stream, response) = connect_async(request).await.map_err(|e| match e {
+            tungstenite::Error::Protocol(ProtocolError::SecWebSocketSubProtocolError(_)) => ClientError::NotNegotiated,
+            e => e.into(),
+        })?;
+        let accepted = response.headers().get("sec-websocket-protocol").and_then(|value| value.to_str().ok());
+        match negotiate(accepted) {
+            Protocol::Json { version } => Ok(Client { stream, version }),
+            Protocol::Text => Err(ClientError::NotNegotiated),
+        }
+    }
+
+    /// The protocol version agreed with the server.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Sends a request to the server.
+    pub async fn send(&mut self, request: &Request) -> Result<(), ClientError> {
+        let json = serde_json::to_string(request)?;
+        self.stream.send(Message::text(json)).await?;
+        Ok(())
+    }
+
+    /// Waits for the next event from the server.
+    ///
+    /// # Returns
+    /// * `Some(Ok(Event))` - The next event.
+    /// * `Some(Err(ClientError))` - If the connection failed or the server sent something that is not an event.
+    /// * `None` - If the server closed the connection.
+    pub async fn next_event(&mut self) -> Option<Result<Event, ClientError>> {
+        loop {
+            let message = match self.stream.next().await? {
+                Ok(message) => message,
+                Err(e) => return Some(Err(e.into())),
+            };
+            match message {
+                Message::Text(text) => {
+                    let event = serde_json::from_str::<VersionedEvent>(text.as_str());
+                    return Some(event.map(|message| message.event).map_err(ClientError::from));
+                }
+                Message::Close(_) => return None,
+                // pings are answered by the WebSocket library
+                _ => continue,
+            }
+        }
+    }
+
+    /// Closes the connection.
+    pub async fn close(mut self) -> Result<(), ClientError> {
+        self.stream.close(None).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_hdr_async;
+    use tokio_tungstenite::tungstenite::handshake::server::{Request as HandshakeRequest, Response};
+
+    /// Starts a server that accepts one connection, echoing back the offered subprotocol if `accept` is set, then sends `event` and replies to the first request with an error naming it.
+    #[allow(clippy::result_large_err)] // the handshake callback's error type is set by tungstenite
+    async fn serve_once(accept: bool, event: Event) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let callback = |request: &HandshakeRequest, mut response: Response| {
+                let offered = request.headers().get("sec-websocket-protocol").and_then(|value| value.to_str().ok());
+                if let (true, Some(subprotocol)) = (accept, negotiate(offered).subprotocol()) {
+                    response.headers_mut().insert("sec-websocket-protocol", subprotocol.parse().unwrap());
+                }
+                Ok(response)
+            };
+            let Ok(mut ws) = accept_hdr_async(socket, callback).await else {
+                return;
+            };
+            let _ = ws.send(Message::text(event.to_json(1))).await;
+            if let Some(Ok(Message::Text(text))) = ws.next().await {
+                let request: Request = serde_json::from_str(text.as_str()).unwrap();
+                let reply = Event::Error { message: format!("{:?}", request) };
+                let _ = ws.send(Message::text(reply.to_json(1))).await;
+            }
+            let _ = ws.close(None).await;
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_requests_and_events_round_trip() {
+        let dealt = Event::HandDealt { cards: vec!["Ah".parse().unwrap(), "RJ".parse().unwrap()] };
+        let url = serve_once(true, dealt.clone()).await;
+        let mut client = Client::connect(&url).await.unwrap();
+        assert_eq!(client.version(), 1);
+        assert_eq!(client.next_event().await.unwrap().unwrap(), dealt);
+
+        client.send(&Request::Ready).await.unwrap();
+        assert_eq!(client.next_event().await.unwrap().unwrap(), Event::Error { message: "Ready".to_string() });
+        assert!(client.next_event().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_text_only_server_is_rejected() {
+        let url = serve_once(false, Event::PotUpdated { pot: 0 }).await;
+        assert!(matches!(Client::connect(&url).await, Err(ClientError::NotNegotiated)));
+    }
+}
@@ -0,0 +1,348 @@
+//! A module for representing playing cards
+//! 
+//! This module contains the `Card`, `Rank` and `Suit` types shared by the deck, the hand display and the hand evaluators, so every part of the server, and every client, agrees on how a card's rank and suit are read.
+//! 
+//! Cards can be parsed from and formatted to the short notation used by most poker tools, such as `Ah` for the Ace of Hearts and `Td` for the Ten of Diamonds. They can also be shown with Unicode suit symbols, such as `A♥`.
+//! 
+//! Variants that play with jokers use a red and a black joker, written `RJ` and `BJ` in short notation.
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject, StringValidation};
+use schemars::JsonSchema;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// The rank of a card, from Two up to Ace. Jokers have their own rank, which sits above the Ace but is normally played as a wild card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rank {
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+    Ace,
+    Joker,
+}
+
+impl Rank {
+    /// Every rank of a standard deck, Ace through King, in the order a new deck is sorted.
+    pub const ALL: [Rank; 13] = [
+        Rank::Ace,
+        Rank::Two,
+        Rank::Three,
+        Rank::Four,
+        Rank::Five,
+        Rank::Six,
+        Rank::Seven,
+        Rank::Eight,
+        Rank::Nine,
+        Rank::Ten,
+        Rank::Jack,
+        Rank::Queen,
+        Rank::King,
+    ];
+
+    /// The value of the rank with Aces high, from 2 (Two) to 14 (Ace).
+    pub fn value(self) -> i32 {
+        self as i32 + 2
+    }
+
+    /// The value of the rank with Aces low, from 1 (Ace) to 13 (King).
+    pub fn low_value(self) -> i32 {
+        match self {
+            Rank::Ace => 1,
+            _ => self.value(),
+        }
+    }
+
+    /// The single character used for the rank in short notation.
+    pub fn to_char(self) -> char {
+        match self {
+            Rank::Two => '2',
+            Rank::Three => '3',
+            Rank::Four => '4',
+            Rank::Five => '5',
+            Rank::Six => '6',
+            Rank::Seven => '7',
+            Rank::Eight => '8',
+            Rank::Nine => '9',
+            Rank::Ten => 'T',
+            Rank::Jack => 'J',
+            Rank::Queen => 'Q',
+            Rank::King => 'K',
+            Rank::Ace => 'A',
+            Rank::Joker => '*',
+        }
+    }
+
+    /// The full name of the rank, such as "Queen" or "7".
+    pub fn name(self) -> String {
+        match self {
+            Rank::Jack => "Jack".to_string(),
+            Rank::Queen => "Queen".to_string(),
+            Rank::King => "King".to_string(),
+            Rank::Ace => "Ace".to_string(),
+            Rank::Joker => "Joker".to_string(),
+            _ => self.value().to_string(),
+        }
+    }
+}
+
+/// The suit of a card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Suit {
+    Hearts,
+    Diamonds,
+    Spades,
+    Clubs,
+}
+
+impl Suit {
+    /// Every suit, in the order a new deck is sorted.
+    pub const ALL: [Suit; 4] = [Suit::Hearts, Suit::Diamonds, Suit::Spades, Suit::Clubs];
+
+    /// The lowercase letter used for the suit in short notation.
+    pub fn to_char(self) -> char {
+        match self {
+            Suit::Hearts => 'h',
+            Suit::Diamonds => 'd',
+            Suit::Spades => 's',
+            Suit::Clubs => 'c',
+        }
+    }
+
+    /// The Unicode symbol for the suit.
+    pub fn symbol(self) -> char {
+        match self {
+            Suit::Hearts => '♥',
+            Suit::Diamonds => '♦',
+            Suit::Spades => '♠',
+            Suit::Clubs => '♣',
+        }
+    }
+
+    /// The full name of the suit.
+    pub fn name(self) -> &'static str {
+        match self {
+            Suit::Hearts => "Hearts",
+            Suit::Diamonds => "Diamonds",
+            Suit::Spades => "Spades",
+            Suit::Clubs => "Clubs",
+        }
+    }
+}
+
+/// A single playing card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Card {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+impl Card {
+    /// The red joker, which takes the Hearts suit to tell it apart from the black joker.
+    pub const RED_JOKER: Card = Card { rank: Rank::Joker, suit: Suit::Hearts };
+    /// The black joker, which takes the Spades suit to tell it apart from the red joker.
+    pub const BLACK_JOKER: Card = Card { rank: Rank::Joker, suit: Suit::Spades };
+
+    /// Creates a card of the given rank and suit.
+    pub fn new(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    /// Whether the card is one of the jokers.
+    pub fn is_joker(&self) -> bool {
+        self.rank == Rank::Joker
+    }
+
+    /// The card shown with its Unicode suit symbol, such as `A♥` or `10♣`. Jokers are shown with the Unicode joker cards.
+    pub fn symbol(&self) -> String {
+        if *self == Card::RED_JOKER {
+            return "🂿".to_string();
+        } else if self.is_joker() {
+            return "🃏".to_string();
+        }
+        let rank = match self.rank {
+            Rank::Ten => "10".to_string(),
+            rank => rank.to_char().to_string(),
+        };
+        format!("{}{}", rank, self.suit.symbol())
+    }
+
+    /// The full name of the card, such as "Ace of Hearts" or "Red Joker".
+    pub fn name(&self) -> String {
+        if *self == Card::RED_JOKER {
+            "Red Joker".to_string()
+        } else if self.is_joker() {
+            "Black Joker".to_string()
+        } else {
+            format!("{} of {}", self.rank.name(), self.suit.name())
+        }
+    }
+}
+
+/// Formats the card in short notation, such as `Ah` or `Td`.
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Card::RED_JOKER {
+            write!(f, "RJ")
+        } else if self.is_joker() {
+            write!(f, "BJ")
+        } else {
+            write!(f, "{}{}", self.rank.to_char(), self.suit.to_char())
+        }
+    }
+}
+
+/// The error returned when a string is not a valid card.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCardError(String);
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid card: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCardError {}
+
+/// Parses a card from short notation. Ranks may be written as `T` or `10`, and suits as a letter or a Unicode symbol, so `Td`, `10d` and `10♦` are all the Ten of Diamonds. Jokers are written `RJ` and `BJ`.
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let error = || ParseCardError(s.to_string());
+        match s.to_ascii_uppercase().as_str() {
+            "RJ" | "🂿" => return Ok(Card::RED_JOKER),
+            "BJ" | "🃏" => return Ok(Card::BLACK_JOKER),
+            _ => {}
+        }
+        let suit_char = s.chars().last().ok_or_else(error)?;
+        let rank_str = &s[..s.len() - suit_char.len_utf8()];
+
+        let rank = match rank_str.to_ascii_uppercase().as_str() {
+            "2" => Rank::Two,
+            "3" => Rank::Three,
+            "4" => Rank::Four,
+            "5" => Rank::Five,
+            "6" => Rank::Six,
+            "7" => Rank::Seven,
+            "8" => Rank::Eight,
+            "9" => Rank::Nine,
+            "T" | "10" => Rank::Ten,
+            "J" => Rank::Jack,
+            "Q" => Rank::Queen,
+            "K" => Rank::King,
+            "A" => Rank::Ace,
+            _ => return Err(error()),
+        };
+        let suit = match suit_char.to_ascii_lowercase() {
+            'h' | '♥' => Suit::Hearts,
+            'd' | '♦' => Suit::Diamonds,
+            's' | '♠' => Suit::Spades,
+            'c' | '♣' => Suit::Clubs,
+            _ => return Err(error()),
+        };
+        Ok(Card { rank, suit })
+    }
+}
+
+/// Cards are sent to clients in short notation, such as `"Ah"`.
+impl Serialize for Card {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Card {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl JsonSchema for Card {
+    fn schema_name() -> String {
+        "Card".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^([2-9TJQKA][shdc]|RJ|BJ)$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_values() {
+        assert_eq!(Rank::Two.value(), 2);
+        assert_eq!(Rank::King.value(), 13);
+        assert_eq!(Rank::Ace.value(), 14);
+        assert_eq!(Rank::Ace.low_value(), 1);
+        assert_eq!(Rank::King.low_value(), 13);
+        assert!(Rank::Ace > Rank::King);
+    }
+
+    #[test]
+    fn test_parse_and_format() {
+        let card: Card = "Ah".parse().unwrap();
+        assert_eq!(card, Card::new(Rank::Ace, Suit::Hearts));
+        assert_eq!(card.to_string(), "Ah");
+        assert_eq!(card.symbol(), "A♥");
+        assert_eq!(card.name(), "Ace of Hearts");
+
+        let ten: Card = "Td".parse().unwrap();
+        assert_eq!(ten, "10d".parse().unwrap());
+        assert_eq!(ten, "10♦".parse().unwrap());
+        assert_eq!(ten.to_string(), "Td");
+        assert_eq!(ten.symbol(), "10♦");
+    }
+
+    #[test]
+    fn test_round_trip_every_card() {
+        for suit in Suit::ALL {
+            for rank in Rank::ALL {
+                let card = Card::new(rank, suit);
+                assert_eq!(card.to_string().parse::<Card>(), Ok(card));
+                assert_eq!(card.symbol().parse::<Card>(), Ok(card));
+            }
+        }
+    }
+
+    #[test]
+    fn test_jokers() {
+        assert!(Card::RED_JOKER.is_joker());
+        assert!(!Card::new(Rank::Ace, Suit::Hearts).is_joker());
+        assert_ne!(Card::RED_JOKER, Card::BLACK_JOKER);
+        for joker in [Card::RED_JOKER, Card::BLACK_JOKER] {
+            assert_eq!(joker.to_string().parse::<Card>(), Ok(joker));
+            assert_eq!(joker.symbol().parse::<Card>(), Ok(joker));
+        }
+        assert_eq!(Card::BLACK_JOKER.name(), "Black Joker");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for input in ["", "A", "1h", "Ax", "AAh", "11s"] {
+            assert!(input.parse::<Card>().is_err(), "{} should not parse", input);
+        }
+    }
+}
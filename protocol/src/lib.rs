@@ -0,0 +1,696 @@
+//! Shared types for the poker server's JSON protocol
+//!
+//! This crate holds everything a client needs to talk to the server in JSON: the requests it can send, the events it receives and the `Card` type they carry. The server and the `poker-client` crate both build on these types, so the two sides always agree on the wire format.
+//!
+//! A client asks for JSON by offering the `poker-json.v1` subprotocol in the `Sec-WebSocket-Protocol` header. The number is the protocol version. A client may offer several versions and the server picks the highest one it supports, echoing it back in the handshake; a connection that offers none stays in text mode.
+//!
+//! Requests look like `{"type": "join_lobby", "name": "table1"}` and `{"type": "action", "action": "raise", "amount": 60}`. Every event carries the protocol version, such as `{"version": 1, "type": "pot_updated", "pot": 120}`. Prompts and notices that have no event of their own are sent as `message` events.
+//!
+//! [`schema`] describes every request and event as JSON Schema, for clients and test tools that want to validate traffic.
+pub mod card;
+
+use card::Card;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The WebSocket subprotocol a client offers to use JSON, followed by the version number.
+pub const SUBPROTOCOL_PREFIX: &str = "poker-json.v";
+
+/// The JSON protocol versions this server speaks, oldest first.
+pub const SUPPORTED_VERSIONS: [u32; 1] = [1];
+
+/// The protocol a connection uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Text,
+    Json { version: u32 },
+}
+
+impl Protocol {
+    /// The subprotocol to confirm in the WebSocket handshake, if any.
+    pub fn subprotocol(&self) -> Option<String> {
+        match self {
+            Protocol::Text => None,
+            Protocol::Json { version } => Some(format!("{}{}", SUBPROTOCOL_PREFIX, version)),
+        }
+    }
+}
+
+/// Picks the protocol for a new connection.
+///
+/// # Arguments
+/// * `offered` - The client's `Sec-WebSocket-Protocol` header, a comma-separated list of subprotocols.
+///
+/// # Returns
+/// * `Protocol` - JSON at the highest supported version the client offered, or text if it offered none.
+pub fn negotiate(offered: Option<&str>) -> Protocol {
+    offered
+        .unwrap_or("")
+        .split(',')
+        .filter_map(|subprotocol| subprotocol.trim().strip_prefix(SUBPROTOCOL_PREFIX)?.parse::<u32>().ok())
+        .filter(|version| SUPPORTED_VERSIONS.contains(version))
+        .max()
+        .map_or(Protocol::Text, |version| Protocol::Json { version })
+}
+
+/// A request sent by a JSON client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Login { username: String, password: String },
+    Register { username: String, password: String },
+    /// Resumes a session started on another connection, with the token from its `session_started` event.
+    Resume { token: String },
+    CreateLobby {
+        name: String,
+        #[serde(default = "default_game")]
+        game: String,
+        /// Stakes, betting limit and action clock, written as in the text menu, such as `"blinds=5/10"`.
+        #[serde(default)]
+        settings: Vec<String>,
+    },
+    JoinLobby { name: String },
+    /// Asks for a player's statistics by ID or name, or the player's own if `player` is left out.
+    PlayerStats {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        player: Option<String>,
+    },
+    /// Asks for a player's VPIP, preflop raise rate, aggression and showdown stats in each variant, by ID or name, or the player's own if `player` is left out.
+    VariantStats {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        player: Option<String>,
+    },
+    /// Asks for a page of a leaderboard.
+    Leaderboard {
+        metric: LeaderboardMetric,
+        /// The page to show, from 1.
+        #[serde(default = "first_page")]
+        page: u32,
+    },
+    /// Asks for the player's own result in a finished game.
+    GameResult { game_number: i64 },
+    /// Resets the game numbering and every player's stats. Only administrators may do this.
+    ResetHistory,
+    LeaveLobby,
+    Ready,
+    Quit,
+    Action {
+        action: ActionKind,
+        /// The total to bet or raise to.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        amount: Option<i32>,
+        /// The positions, starting at 1, of the cards to exchange in a draw.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        cards: Vec<usize>,
+    },
+}
+
+fn default_game() -> String {
+    "draw".to_string()
+}
+
+fn first_page() -> u32 {
+    1
+}
+
+/// A move in a betting or drawing round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Check,
+    Call,
+    Bet,
+    Raise,
+    AllIn,
+    Fold,
+    Draw,
+    StandPat,
+}
+
+/// What a leaderboard ranks players by.
+///
+/// In the text menu a metric is written `wallet`, `wins`, `winrate`, `winrate=MIN_GAMES`, `profit` or `profit=DAYS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum LeaderboardMetric {
+    /// The most chips.
+    Wallet,
+    /// The most games won.
+    Wins,
+    /// The highest share of games won, among players with at least `min_games` games.
+    WinRate { min_games: u32 },
+    /// The most chips won over the last `days` days, or over every recorded game if `days` is left out.
+    NetProfit {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        days: Option<u32>,
+    },
+}
+
+impl LeaderboardMetric {
+    /// The minimum number of games for the win rate leaderboard when none is given.
+    pub const DEFAULT_MIN_GAMES: u32 = 5;
+
+    /// A title for the leaderboard, such as "win rate (at least 5 games)".
+    pub fn title(&self) -> String {
+        match self {
+            LeaderboardMetric::Wallet => "wallet".to_string(),
+            LeaderboardMetric::Wins => "games won".to_string(),
+            LeaderboardMetric::WinRate { min_games } => format!("win rate (at least {} games)", min_games),
+            LeaderboardMetric::NetProfit { days: None } => "net profit".to_string(),
+            LeaderboardMetric::NetProfit { days: Some(days) } => format!("net profit (last {} days)", days),
+        }
+    }
+}
+
+/// Formats the metric as it is written in the text menu, such as `winrate=5`.
+impl fmt::Display for LeaderboardMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LeaderboardMetric::Wallet => write!(f, "wallet"),
+            LeaderboardMetric::Wins => write!(f, "wins"),
+            LeaderboardMetric::WinRate { min_games } => write!(f, "winrate={}", min_games),
+            LeaderboardMetric::NetProfit { days: None } => write!(f, "profit"),
+            LeaderboardMetric::NetProfit { days: Some(days) } => write!(f, "profit={}", days),
+        }
+    }
+}
+
+impl FromStr for LeaderboardMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = match s.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (s, None),
+        };
+        let number = |value: &str| value.parse::<u32>().map_err(|_| format!("{} is not a number.", value));
+        match (name.to_ascii_lowercase().as_str(), value) {
+            ("wallet", None) => Ok(LeaderboardMetric::Wallet),
+            ("wins", None) => Ok(LeaderboardMetric::Wins),
+            ("winrate", None) => Ok(LeaderboardMetric::WinRate { min_games: LeaderboardMetric::DEFAULT_MIN_GAMES }),
+            ("winrate", Some(min_games)) => Ok(LeaderboardMetric::WinRate { min_games: number(min_games)? }),
+            ("profit", None) => Ok(LeaderboardMetric::NetProfit { days: None }),
+            ("profit", Some(days)) => Ok(LeaderboardMetric::NetProfit { days: Some(number(days)?) }),
+            _ => Err(format!("Unknown leaderboard {}. Use wallet, wins, winrate[=MIN_GAMES] or profit[=DAYS].", s)),
+        }
+    }
+}
+
+impl Request {
+    /// Translates the request into the text commands it stands for.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<String>)` - The commands, in the order the menus read them.
+    /// * `Err(String)` - A message for the client if the request is missing something.
+    pub fn to_commands(&self) -> Result<Vec<String>, String> {
+        let no_spaces = |field: &str, value: &str| {
+            if value.is_empty() || value.contains(char::is_whitespace) {
+                Err(format!("{} must be a single word.", field))
+            } else {
+                Ok(())
+            }
+        };
+        Ok(match self {
+            Request::Login { username, password } => vec!["login".to_string(), username.clone(), password.clone()],
+            Request::Register { username, password } => vec!["register".to_string(), username.clone(), password.clone()],
+            Request::Resume { token } => vec!["resume".to_string(), token.clone()],
+            Request::CreateLobby { name, game, settings } => {
+                no_spaces("Lobby name", name)?;
+                for setting in settings {
+                    no_spaces("Lobby setting", setting)?;
+                }
+                vec![[vec!["create", name, game], settings.iter().map(String::as_str).collect()].concat().join(" ")]
+            }
+            Request::JoinLobby { name } => {
+                no_spaces("Lobby name", name)?;
+                vec![format!("join {}", name)]
+            }
+            Request::PlayerStats { player: None } => vec!["stats".to_string()],
+            Request::PlayerStats { player: Some(player) } => {
+                no_spaces("Player", player)?;
+                vec![format!("stats {}", player)]
+            }
+            Request::VariantStats { player: None } => vec!["pokerstats".to_string()],
+            Request::VariantStats { player: Some(player) } => {
+                no_spaces("Player", player)?;
+                vec![format!("pokerstats {}", player)]
+            }
+            Request::Leaderboard { metric, page } => vec![format!("leaderboard {} {}", metric, page)],
+            Request::GameResult { game_number } => vec![format!("game {}", game_number)],
+            Request::ResetHistory => vec!["reset".to_string()],
+            Request::LeaveLobby => vec!["leave".to_string()],
+            Request::Ready => vec!["ready".to_string()],
+            Request::Quit => vec!["quit".to_string()],
+            Request::Action { action, amount, cards } => vec![match action {
+                ActionKind::Check => "check".to_string(),
+                ActionKind::Call => "call".to_string(),
+                ActionKind::Fold => "fold".to_string(),
+                ActionKind::AllIn => "allin".to_string(),
+                ActionKind::Bet | ActionKind::Raise => {
+                    let amount = amount.ok_or("A bet or raise needs an amount.")?;
+                    format!("raise {}", amount)
+                }
+                ActionKind::StandPat => "stand".to_string(),
+                ActionKind::Draw => {
+                    if cards.is_empty() {
+                        return Err("A draw needs the cards to exchange.".to_string());
+                    }
+                    format!("draw {}", cards.iter().map(|card| card.to_string()).collect::<Vec<_>>().join(","))
+                }
+            }],
+        })
+    }
+}
+
+/// One pot awarded at the showdown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PotResult {
+    /// `Main pot` or `Side pot N`.
+    pub name: String,
+    pub amount: i32,
+    pub eligible: Vec<String>,
+    pub winners: Vec<String>,
+    /// The winning hand, such as `Full House (Kings over 5s)` or `Flush (Ace high)`, or `None` if nobody else was left to contest the pot.
+    pub hand: Option<String>,
+}
+
+/// A player's lifetime statistics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerStats {
+    /// The player's stored ID.
+    pub id: String,
+    pub name: String,
+    pub games_played: i32,
+    pub games_won: i32,
+    pub wallet: i32,
+    /// The share of games played that the player won, from 0 to 1.
+    pub win_rate: f64,
+    /// Chips won or lost over every recorded game since the last reset.
+    pub net_chips: i32,
+}
+
+/// A player's betting and showdown stats in one poker variant. Rates run from 0 to 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VariantStats {
+    pub variant: String,
+    pub hands: i32,
+    /// The share of hands in which the player voluntarily put chips in (VPIP). Forced bets do not count.
+    pub vpip: f64,
+    /// The share of hands in which the player bet or raised in the first betting round (PFR).
+    pub preflop_raise_rate: f64,
+    /// Bets and raises per call, left out if the player has never called.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggression_factor: Option<f64>,
+    /// The share of hands the player took to a showdown (WTSD).
+    pub went_to_showdown: f64,
+    /// The share of showdowns the player won (W$SD), left out if they have not been to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub won_at_showdown: Option<f64>,
+    /// The most chips the player took from the pots in one game, left out if they have not won any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub biggest_pot: Option<i32>,
+}
+
+/// A player's place on a leaderboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LeaderboardEntry {
+    /// The player's rank, from 1. Tied players share a rank and are listed by name.
+    pub rank: i64,
+    pub player: PlayerStats,
+    /// Chips won over the leaderboard's window, for net profit leaderboards only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub net_profit: Option<i64>,
+}
+
+/// An event sent to a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A prompt or notice with no event of its own.
+    Message { text: String },
+    /// A request the server could not understand.
+    Error { message: String },
+    /// The player logged in. `token` resumes the session on a new connection.
+    SessionStarted { username: String, token: String },
+    /// The player's private cards, after the deal or a draw.
+    HandDealt { cards: Vec<Card> },
+    /// It is the player's turn to bet.
+    YourTurn {
+        call_amount: i32,
+        pot: i32,
+        wallet: i32,
+        /// The smallest and largest total the player may raise to, if they may raise.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        raise_range: Option<(i32, i32)>,
+    },
+    /// A player made a move. `amount` is the total bet for bets and raises, the chips added for calls, and the number of cards exchanged for draws.
+    PlayerActed {
+        player: String,
+        action: ActionKind,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount: Option<i32>,
+    },
+    PotUpdated { pot: i32 },
+    ShowdownResult { pots: Vec<PotResult> },
+    /// Everything a player needs to pick up a hand again after reconnecting.
+    TableState {
+        lobby: String,
+        hand: Vec<Card>,
+        board: Vec<Card>,
+        pot: i32,
+        /// The player whose turn it is, if the hand is waiting on someone.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        to_act: Option<String>,
+    },
+    /// The statistics asked for with a `player_stats` request.
+    PlayerStats(PlayerStats),
+    /// A player's stats per variant, asked for with a `variant_stats` request.
+    VariantStats { player: String, variants: Vec<VariantStats> },
+    /// A page of a leaderboard, asked for with a `leaderboard` request.
+    Leaderboard { metric: LeaderboardMetric, page: u32, entries: Vec<LeaderboardEntry> },
+}
+
+impl Event {
+    /// Renders the event for a text client.
+    pub fn to_text(&self) -> String {
+        match self {
+            Event::Message { text } => text.clone(),
+            Event::Error { message } => message.clone(),
+            Event::SessionStarted { token, .. } => {
+                format!("Session token: {}\nUse option 4 with this token to resume your session on a new connection.", token)
+            }
+            Event::HandDealt { cards } => {
+                let cards: Vec<String> = cards
+                    .iter()
+                    .enumerate()
+                    .map(|(i, card)| format!("{}. {}  {}\n", i + 1, card.symbol(), card.name()))
+                    .collect();
+                format!("Your hand:\n{}", cards.concat())
+            }
+            Event::YourTurn { call_amount, pot, wallet, .. } => format!(
+                "Choose an option:\n1. Check\n2. Raise\n3. Call\n4. Fold\n5. All-in\n\nYour amount to call: {}\nCurrent Pot: {}\nCurrent Wallet: {}",
+                call_amount, pot, wallet
+            ),
+            Event::PlayerActed { player, action, amount } => {
+                let amount = amount.unwrap_or(0);
+                match action {
+                    ActionKind::Check => format!("{} has checked.", player),
+                    ActionKind::Call => format!("{} has called the bet.", player),
+                    ActionKind::Bet => format!("{} has bet {}.", player, amount),
+                    ActionKind::Raise => format!("{} has raised to {}.", player, amount),
+                    ActionKind::AllIn => format!("{} has gone all in!", player),
+                    ActionKind::Fold => format!("{} has folded.", player),
+                    ActionKind::Draw => format!("{} has exchanged {} cards.", player, amount),
+                    ActionKind::StandPat => format!("{} stands pat.", player),
+                }
+            }
+            Event::PotUpdated { pot } => format!("Current pot: {}", pot),
+            Event::TableState { lobby, hand, board, pot, to_act } => {
+                let cards = |cards: &[Card]| cards.iter().map(Card::symbol).collect::<Vec<_>>().join(" ");
+                let mut text = format!("Lobby: {}\nYour hand: {}\n", lobby, cards(hand));
+                if !board.is_empty() {
+                    text.push_str(&format!("Board: {}\n", cards(board)));
+                }
+                text.push_str(&format!("Current pot: {}", pot));
+                if let Some(player) = to_act {
+                    text.push_str(&format!("\nWaiting on: {}", player));
+                }
+                text
+            }
+            Event::PlayerStats(stats) => format!(
+                "Player Stats for {} (ID {}): Games Played: {}, Games Won: {}, Win Rate: {:.1}%, Wallet: {}, Net Chips: {:+}",
+                stats.name,
+                stats.id,
+                stats.games_played,
+                stats.games_won,
+                stats.win_rate * 100.0,
+                stats.wallet,
+                stats.net_chips
+            ),
+            Event::VariantStats { player, variants } => {
+                if variants.is_empty() {
+                    return format!("No hands recorded for {} yet.", player);
+                }
+                let percent = |rate: f64| format!("{:.1}%", rate * 100.0);
+                let mut text = format!("Poker stats for {}:", player);
+                for stats in variants {
+                    text.push_str(&format!(
+                        "\n{} ({} hands): VPIP {}, PFR {}, AF {}, WTSD {}, W$SD {}, biggest pot {}",
+                        stats.variant,
+                        stats.hands,
+                        percent(stats.vpip),
+                        percent(stats.preflop_raise_rate),
+                        stats.aggression_factor.map_or("-".to_string(), |factor| format!("{:.2}", factor)),
+                        percent(stats.went_to_showdown),
+                        stats.won_at_showdown.map_or("-".to_string(), percent),
+                        stats.biggest_pot.map_or("-".to_string(), |pot| pot.to_string())
+                    ));
+                }
+                text
+            }
+            Event::Leaderboard { metric, page, entries } => {
+                let mut text = format!("Leaderboard by {}, page {}:", metric.title(), page);
+                if entries.is_empty() {
+                    text.push_str("\nNo players on this page.");
+                }
+                for entry in entries {
+                    let player = &entry.player;
+                    let score = match metric {
+                        LeaderboardMetric::Wallet => format!("{} chips", player.wallet),
+                        LeaderboardMetric::Wins => format!("{} wins", player.games_won),
+                        LeaderboardMetric::WinRate { .. } => {
+                            format!("{:.1}% ({}/{})", player.win_rate * 100.0, player.games_won, player.games_played)
+                        }
+                        LeaderboardMetric::NetProfit { .. } => format!("{:+} chips", entry.net_profit.unwrap_or(0)),
+                    };
+                    text.push_str(&format!("\n{}. {} - {}", entry.rank, player.name, score));
+                }
+                text
+            }
+            Event::ShowdownResult { pots } => pots
+                .iter()
+                .map(|pot| {
+                    let hand = match &pot.hand {
+                        Some(hand) => format!("with {}", hand),
+                        None => "uncontested".to_string(),
+                    };
+                    format!("{}: {} (eligible: {}) won by {} {}", pot.name, pot.amount, pot.eligible.join(", "), pot.winners.join(", "), hand)
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Renders the event for a JSON client, tagged with the protocol version.
+    pub fn to_json(&self, version: u32) -> String {
+        let message = VersionedEvent { version, event: self.clone() };
+        serde_json::to_string(&message).expect("events always serialize")
+    }
+}
+
+/// An event as it is sent to a JSON client, tagged with the protocol version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VersionedEvent {
+    pub version: u32,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Describes the JSON protocol as JSON Schema.
+///
+/// # Returns
+/// * `serde_json::Value` - A document with the protocol version and subprotocol, a `request` schema that every message from a client matches, and an `event` schema that every message from the server matches.
+pub fn schema() -> serde_json::Value {
+    let version = *SUPPORTED_VERSIONS.last().expect("at least one version is supported");
+    serde_json::json!({
+        "version": version,
+        "subprotocol": Protocol::Json { version }.subprotocol(),
+        "request": schema_for!(Request),
+        "event": schema_for!(VersionedEvent),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate(None), Protocol::Text);
+        assert_eq!(negotiate(Some("chat, superchat")), Protocol::Text);
+        assert_eq!(negotiate(Some("poker-json.v1")), Protocol::Json { version: 1 });
+        // unsupported versions are skipped
+        assert_eq!(negotiate(Some("poker-json.v9, poker-json.v1")), Protocol::Json { version: 1 });
+        assert_eq!(Protocol::Json { version: 1 }.subprotocol(), Some("poker-json.v1".to_string()));
+    }
+
+    #[test]
+    fn test_requests_become_commands() {
+        let commands = |json: &str| serde_json::from_str::<Request>(json).unwrap().to_commands();
+        assert_eq!(
+            commands(r#"{"type": "login", "username": "alice", "password": "hunter2"}"#),
+            Ok(vec!["login".to_string(), "alice".to_string(), "hunter2".to_string()])
+        );
+        assert_eq!(commands(r#"{"type": "resume", "token": "ab12"}"#), Ok(vec!["resume".to_string(), "ab12".to_string()]));
+        assert!(serde_json::from_str::<Request>(r#"{"type": "login", "username": "alice"}"#).is_err());
+        assert_eq!(
+            commands(r#"{"type": "create_lobby", "name": "table1", "game": "holdem", "settings": ["blinds=5/10", "potlimit"]}"#),
+            Ok(vec!["create table1 holdem blinds=5/10 potlimit".to_string()])
+        );
+        assert_eq!(commands(r#"{"type": "create_lobby", "name": "table2"}"#), Ok(vec!["create table2 draw".to_string()]));
+        assert_eq!(commands(r#"{"type": "join_lobby", "name": "table1"}"#), Ok(vec!["join table1".to_string()]));
+        assert_eq!(commands(r#"{"type": "player_stats"}"#), Ok(vec!["stats".to_string()]));
+        assert_eq!(commands(r#"{"type": "player_stats", "player": "bob"}"#), Ok(vec!["stats bob".to_string()]));
+        assert_eq!(commands(r#"{"type": "variant_stats", "player": "bob"}"#), Ok(vec!["pokerstats bob".to_string()]));
+        assert_eq!(
+            commands(r#"{"type": "leaderboard", "metric": {"by": "win_rate", "min_games": 10}, "page": 2}"#),
+            Ok(vec!["leaderboard winrate=10 2".to_string()])
+        );
+        assert_eq!(commands(r#"{"type": "leaderboard", "metric": {"by": "net_profit"}}"#), Ok(vec!["leaderboard profit 1".to_string()]));
+        assert_eq!(commands(r#"{"type": "game_result", "game_number": 3}"#), Ok(vec!["game 3".to_string()]));
+        assert_eq!(commands(r#"{"type": "ready"}"#), Ok(vec!["ready".to_string()]));
+        assert_eq!(commands(r#"{"type": "action", "action": "raise", "amount": 60}"#), Ok(vec!["raise 60".to_string()]));
+        assert_eq!(commands(r#"{"type": "action", "action": "draw", "cards": [1, 4]}"#), Ok(vec!["draw 1,4".to_string()]));
+        assert_eq!(commands(r#"{"type": "action", "action": "all_in"}"#), Ok(vec!["allin".to_string()]));
+
+        assert!(commands(r#"{"type": "action", "action": "bet"}"#).is_err());
+        assert!(commands(r#"{"type": "join_lobby", "name": "two words"}"#).is_err());
+        assert!(serde_json::from_str::<Request>(r#"{"type": "teleport"}"#).is_err());
+    }
+
+    #[test]
+    fn test_events_carry_the_version() {
+        let event = Event::HandDealt { cards: vec!["Ah".parse().unwrap(), "Td".parse().unwrap()] };
+        let json: serde_json::Value = serde_json::from_str(&event.to_json(1)).unwrap();
+        assert_eq!(json, serde_json::json!({"version": 1, "type": "hand_dealt", "cards": ["Ah", "Td"]}));
+
+        let acted = Event::PlayerActed { player: "bob".to_string(), action: ActionKind::AllIn, amount: Some(250) };
+        let json: serde_json::Value = serde_json::from_str(&acted.to_json(1)).unwrap();
+        assert_eq!(json, serde_json::json!({"version": 1, "type": "player_acted", "player": "bob", "action": "all_in", "amount": 250}));
+    }
+
+    #[test]
+    fn test_event_text() {
+        let acted = Event::PlayerActed { player: "bob".to_string(), action: ActionKind::Raise, amount: Some(60) };
+        assert_eq!(acted.to_text(), "bob has raised to 60.");
+        assert_eq!(Event::PotUpdated { pot: 120 }.to_text(), "Current pot: 120");
+        let dealt = Event::HandDealt { cards: vec!["Ah".parse().unwrap()] };
+        assert_eq!(dealt.to_text(), "Your hand:\n1. A♥  Ace of Hearts\n");
+        let table = Event::TableState {
+            lobby: "table1".to_string(),
+            hand: vec!["Ah".parse().unwrap(), "Td".parse().unwrap()],
+            board: vec!["2c".parse().unwrap()],
+            pot: 40,
+            to_act: Some("bob".to_string()),
+        };
+        assert_eq!(table.to_text(), "Lobby: table1\nYour hand: A♥ 10♦\nBoard: 2♣\nCurrent pot: 40\nWaiting on: bob");
+        let showdown = Event::ShowdownResult {
+            pots: vec![
+                PotResult {
+                    name: "Main pot".to_string(),
+                    amount: 90,
+                    eligible: vec!["alice".to_string(), "bob".to_string()],
+                    winners: vec!["alice".to_string()],
+                    hand: Some("Pair (Kings)".to_string()),
+                },
+                PotResult {
+                    name: "Side pot 1".to_string(),
+                    amount: 40,
+                    eligible: vec!["bob".to_string()],
+                    winners: vec!["bob".to_string()],
+                    hand: None,
+                },
+            ],
+        };
+        assert_eq!(
+            showdown.to_text(),
+            "Main pot: 90 (eligible: alice, bob) won by alice with Pair (Kings)\nSide pot 1: 40 (eligible: bob) won by bob uncontested"
+        );
+        let stats = Event::PlayerStats(PlayerStats {
+            id: "42".to_string(),
+            name: "bob".to_string(),
+            games_played: 8,
+            games_won: 2,
+            wallet: 900,
+            win_rate: 0.25,
+            net_chips: -100,
+        });
+        assert_eq!(
+            stats.to_text(),
+            "Player Stats for bob (ID 42): Games Played: 8, Games Won: 2, Win Rate: 25.0%, Wallet: 900, Net Chips: -100"
+        );
+        let variants = Event::VariantStats {
+            player: "bob".to_string(),
+            variants: vec![VariantStats {
+                variant: "Texas Hold'em".to_string(),
+                hands: 8,
+                vpip: 0.375,
+                preflop_raise_rate: 0.125,
+                aggression_factor: Some(1.5),
+                went_to_showdown: 0.25,
+                won_at_showdown: None,
+                biggest_pot: None,
+            }],
+        };
+        assert_eq!(
+            variants.to_text(),
+            "Poker stats for bob:\nTexas Hold'em (8 hands): VPIP 37.5%, PFR 12.5%, AF 1.50, WTSD 25.0%, W$SD -, biggest pot -"
+        );
+    }
+
+    #[test]
+    fn test_leaderboard_metrics() {
+        for metric in [
+            LeaderboardMetric::Wallet,
+            LeaderboardMetric::Wins,
+            LeaderboardMetric::WinRate { min_games: 10 },
+            LeaderboardMetric::NetProfit { days: None },
+            LeaderboardMetric::NetProfit { days: Some(7) },
+        ] {
+            assert_eq!(metric.to_string().parse(), Ok(metric));
+        }
+        assert_eq!("winrate".parse(), Ok(LeaderboardMetric::WinRate { min_games: LeaderboardMetric::DEFAULT_MIN_GAMES }));
+        assert!("profit=soon".parse::<LeaderboardMetric>().is_err());
+        assert!("wallet=3".parse::<LeaderboardMetric>().is_err());
+
+        let entry = |rank, name: &str, games_won, games_played| LeaderboardEntry {
+            rank,
+            player: PlayerStats {
+                id: name.to_string(),
+                name: name.to_string(),
+                games_played,
+                games_won,
+                wallet: 1000,
+                win_rate: games_won as f64 / games_played as f64,
+                net_chips: 0,
+            },
+            net_profit: None,
+        };
+        let board = Event::Leaderboard {
+            metric: LeaderboardMetric::WinRate { min_games: 5 },
+            page: 1,
+            entries: vec![entry(1, "alice", 3, 6), entry(1, "bob", 4, 8)],
+        };
+        assert_eq!(
+            board.to_text(),
+            "Leaderboard by win rate (at least 5 games), page 1:\n1. alice - 50.0% (3/6)\n1. bob - 50.0% (4/8)"
+        );
+    }
+
+    #[test]
+    fn test_published_schema_is_current() {
+        // regenerate with `UPDATE_SCHEMA=1 cargo test -p poker-protocol`
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/schema.json");
+        let current = serde_json::to_string_pretty(&schema()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(path, &current).unwrap();
+        }
+        let published = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(published == current, "schema.json is out of date, regenerate it with UPDATE_SCHEMA=1");
+
+        let event = schema()["event"].to_string();
+        assert!(event.contains("hand_dealt") && event.contains("showdown_result"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Read-only HTTP API for dashboards and spectator tools
+//!
+//! The routes in this module answer plain `GET` requests with JSON, so tools can read player stats, recorded games, open lobbies and the leaderboard without opening a WebSocket and walking through the login menu. They read from the same `Database` and server `Lobby` as the game itself and never change anything.
+//!
+//! - `GET /players/{id}/stats` - A player's stats by ID or name.
+//! - `GET /players/{id}/variant_stats` - A player's VPIP, preflop raise rate, aggression and showdown stats in each variant they have played.
+//! - `GET /games/{n}` - A recorded game and every player's result in it.
+//! - `GET /lobbies` - Every game lobby, its settings and who is sitting in it.
+//! - `GET /leaderboard?by=METRIC&page=N&per_page=N` - A page of a leaderboard. `by` is `wallet` (the default), `wins`, `win_rate` with an optional `min_games`, or `net_profit` with an optional window of `days`. Pages hold 10 players unless `per_page` asks for up to 100.
+use crate::database::Database;
+use crate::lobby::{Lobby, GAME_LOBBY_FULL, JOINABLE};
+use crate::protocol::{LeaderboardEntry, LeaderboardMetric, PlayerStats, VariantStats};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+pub const DEFAULT_PAGE_SIZE: u32 = 10;
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// Every route of the API.
+///
+/// # Arguments
+/// * `db` - The database the stats, games and leaderboard are read from.
+/// * `server_lobby` - The server lobby, for the list of game lobbies.
+pub fn routes(
+    db: Arc<Database>,
+    server_lobby: Arc<Mutex<Lobby>>,
+) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    player_stats_route(db.clone())
+        .or(variant_stats_route(db.clone()))
+        .unify()
+        .or(game_route(db.clone()))
+        .unify()
+        .or(leaderboard_route(db))
+        .unify()
+        .or(lobbies_route(server_lobby))
+        .unify()
+}
+
+fn player_stats_route(db: Arc<Database>) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("players" / String / "stats")
+        .and(warp::get())
+        .and_then(move |player: String| {
+            let db = db.clone();
+            async move { Ok::<_, Infallible>(player_stats(&db, &player).await) }
+        })
+}
+
+fn variant_stats_route(db: Arc<Database>) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("players" / String / "variant_stats")
+        .and(warp::get())
+        .and_then(move |player: String| {
+            let db = db.clone();
+            async move { Ok::<_, Infallible>(variant_stats(&db, &player).await) }
+        })
+}
+
+fn game_route(db: Arc<Database>) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("games" / i64)
+        .and(warp::get())
+        .and_then(move |game_number: i64| {
+            let db = db.clone();
+            async move { Ok::<_, Infallible>(game(&db, game_number).await) }
+        })
+}
+
+fn leaderboard_route(db: Arc<Database>) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("leaderboard")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and_then(move |query: HashMap<String, String>| {
+            let db = db.clone();
+            async move { Ok::<_, Infallible>(leaderboard(&db, &query).await) }
+        })
+}
+
+fn lobbies_route(server_lobby: Arc<Mutex<Lobby>>) -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    warp::path!("lobbies")
+        .and(warp::get())
+        .and_then(move || {
+            let server_lobby = server_lobby.clone();
+            async move { Ok::<_, Infallible>(lobbies(&server_lobby).await) }
+        })
+}
+
+/// A JSON error body with the given status.
+fn error(status: StatusCode, message: &str) -> Response {
+    warp::reply::with_status(warp::reply::json(&json!({ "error": message })), status).into_response()
+}
+
+/// Logs a database error and hides it from the caller.
+fn internal_error(e: sqlx::Error) -> Response {
+    eprintln!("API database error: {}", e);
+    error(StatusCode::INTERNAL_SERVER_ERROR, "database error")
+}
+
+async fn player_stats(db: &Database, player: &str) -> Response {
+    match db.player_stats(player).await {
+        Ok(stats) => warp::reply::json(&PlayerStats::from(stats)).into_response(),
+        Err(sqlx::Error::RowNotFound) => error(StatusCode::NOT_FOUND, "no such player"),
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn variant_stats(db: &Database, player: &str) -> Response {
+    let stats = match db.player_stats(player).await {
+        Ok(stats) => stats,
+        Err(sqlx::Error::RowNotFound) => return error(StatusCode::NOT_FOUND, "no such player"),
+        Err(e) => return internal_error(e),
+    };
+    match db.variant_stats(&stats.id).await {
+        Ok(variants) => {
+            let variants = variants.into_iter().map(VariantStats::from).collect::<Vec<_>>();
+            warp::reply::json(&json!({ "id": stats.id, "name": stats.name, "variants": variants })).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn game(db: &Database, game_number: i64) -> Response {
+    match db.game(game_number).await {
+        Ok(Some(game)) => {
+            let results = game
+                .results
+                .iter()
+                .map(|(name, result)| {
+                    json!({
+                        "player": name,
+                        "player_id": result.player_id,
+                        "net_result": result.net_result,
+                        "final_hand": result.final_hand,
+                        "won": result.won,
+                        "showdown": result.showdown,
+                        "collected": result.collected,
+                    })
+                })
+                .collect::<Vec<_>>();
+            warp::reply::json(&json!({
+                "game_number": game.game_number,
+                "variant": game.variant,
+                "lobby": game.lobby,
+                "started_at": game.started_at,
+                "ended_at": game.ended_at,
+                "pot": game.pot,
+                "results": results,
+            }))
+            .into_response()
+        }
+        Ok(None) => error(StatusCode::NOT_FOUND, "no such game"),
+        Err(e) => internal_error(e),
+    }
+}
+
+/// Reads the leaderboard metric and page from the query string.
+fn leaderboard_query(query: &HashMap<String, String>) -> Result<(LeaderboardMetric, u32, u32), String> {
+    let number = |name: &str| -> Result<Option<u32>, String> {
+        query.get(name).map(|value| value.parse::<u32>().map_err(|_| format!("{} must be a number", name))).transpose()
+    };
+    let metric = match query.get("by").map(String::as_str).unwrap_or("wallet") {
+        "wallet" => LeaderboardMetric::Wallet,
+        "wins" => LeaderboardMetric::Wins,
+        "win_rate" => LeaderboardMetric::WinRate {
+            min_games: number("min_games")?.unwrap_or(LeaderboardMetric::DEFAULT_MIN_GAMES),
+        },
+        "net_profit" => LeaderboardMetric::NetProfit { days: number("days")? },
+        _ => return Err("by must be wallet, wins, win_rate or net_profit".to_string()),
+    };
+    let page = number("page")?.unwrap_or(1);
+    let per_page = number("per_page")?.unwrap_or(DEFAULT_PAGE_SIZE);
+    if page == 0 || !(1..=MAX_PAGE_SIZE).contains(&per_page) {
+        return Err(format!("page must be at least 1 and per_page between 1 and {}", MAX_PAGE_SIZE));
+    }
+    Ok((metric, page, per_page))
+}
+
+async fn leaderboard(db: &Database, query: &HashMap<String, String>) -> Response {
+    let (metric, page, per_page) = match leaderboard_query(query) {
+        Ok(query) => query,
+        Err(message) => return error(StatusCode::BAD_REQUEST, &message),
+    };
+    match db.leaderboard(metric, page, per_page).await {
+        Ok(entries) => {
+            let entries = entries.into_iter().map(LeaderboardEntry::from).collect::<Vec<_>>();
+            warp::reply::json(&json!({ "metric": metric, "page": page, "per_page": per_page, "entries": entries })).into_response()
+        }
+        Err(e) => internal_error(e),
+    }
+}
+
+async fn lobbies(server_lobby: &Mutex<Lobby>) -> Response {
+    let (lobbies, names_and_settings) = {
+        let server_lobby = server_lobby.lock().await;
+        let lobbies = server_lobby.lobbies.lock().await.clone();
+        (lobbies, server_lobby.get_lobby_names_and_status().await)
+    };
+    let mut list = Vec::new();
+    for (lobby, (name, _, settings)) in lobbies.iter().zip(names_and_settings) {
+        // a lobby stays locked for the whole game, so who is playing is only known between games
+        let entry: Value = match lobby.try_lock() {
+            Ok(lobby) => {
+                let players = lobby.players.lock().await.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+                let state = match lobby.game_state {
+                    JOINABLE => "waiting",
+                    GAME_LOBBY_FULL => "full",
+                    _ => "in_game",
+                };
+                json!({ "name": name, "settings": settings, "state": state, "players": players, "max_players": lobby.max_player_count })
+            }
+            Err(_) => json!({ "name": name, "settings": settings, "state": "in_game", "players": null }),
+        };
+        list.push(entry);
+    }
+    warp::reply::json(&list).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::GameResult;
+    use sqlx::SqlitePool;
+
+    async fn setup_database() -> Arc<Database> {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql(include_str!("../schema.sql")).execute(&pool).await.unwrap();
+        Arc::new(Database::new(pool))
+    }
+
+    async fn get(filter: &(impl Filter<Extract = (Response,), Error = Rejection> + Clone + 'static), path: &str) -> (StatusCode, Value) {
+        let response = warp::test::request().method("GET").path(path).reply(filter).await;
+        (response.status(), serde_json::from_slice(response.body()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_player_stats_by_id_and_name() {
+        let db = setup_database().await;
+        let id = db.register_player("alice", "pw").await.unwrap();
+        let route = player_stats_route(db);
+
+        let (status, body) = get(&route, &format!("/players/{}/stats", id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["id"], json!(id));
+        assert_eq!(body["name"], "alice");
+        assert_eq!(body["net_chips"], 0);
+        assert_eq!(get(&route, "/players/alice/stats").await.1["id"], json!(id));
+        assert_eq!(get(&route, "/players/nobody/stats").await.0, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_game_and_leaderboard() {
+        let db = setup_database().await;
+        let alice = db.register_player("alice", "pw").await.unwrap();
+        let bob = db.register_player("bob", "pw").await.unwrap();
+        db.start_game("Five Card Draw", "table1").await.unwrap();
+        let results = [
+            GameResult { player_id: alice, net_result: 30, final_hand: Some("Ah Ad Kc Ks 2h".to_string()), won: true, showdown: false, collected: 60 },
+            GameResult { player_id: bob.clone(), net_result: -30, final_hand: None, won: false, showdown: false, collected: 0 },
+        ];
+        db.finish_game(0, 60, &results, &[]).await.unwrap();
+        sqlx::query("UPDATE players SET wallet = 970 WHERE id = ?1").bind(&bob).execute(&*db.pool).await.unwrap();
+
+        let (status, game) = get(&game_route(db.clone()), "/games/0").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(game["lobby"], "table1");
+        assert_eq!(game["pot"], 60);
+        assert_eq!(game["results"][0]["player"], "alice");
+        assert_eq!(game["results"][1]["final_hand"], Value::Null);
+        assert_eq!(get(&game_route(db.clone()), "/games/1").await.0, StatusCode::NOT_FOUND);
+
+        let route = leaderboard_route(db);
+        let (_, board) = get(&route, "/leaderboard").await;
+        assert_eq!(board["metric"], json!({"by": "wallet"}));
+        assert_eq!(board["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(board["entries"][0]["player"]["name"], "alice");
+        let (_, profit) = get(&route, "/leaderboard?by=net_profit&days=7&page=2&per_page=1").await;
+        assert_eq!(profit["entries"][0]["rank"], 2);
+        assert_eq!(profit["entries"][0]["net_profit"], -30);
+        assert_eq!(get(&route, "/leaderboard?per_page=0").await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(get(&route, "/leaderboard?by=luck").await.0, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,851 @@
+//! A module for the betting structure of a lobby
+//!
+//! This module contains the forced bets a lobby is played with, either an ante from every player or a small and big blind, and works out where the dealer button and the blinds sit for each hand. It also contains the betting limit of a lobby, which decides how much a player may raise.
+//!
+//! The `BettingRound` state machine runs a single betting round. It takes each player's action, checks it against the rules, and returns the events that followed and whose turn is next. It does no I/O, so the lobby drives it over the WebSocket and the rules can be tested on their own.
+//!
+//! Seats are the players' indices in the lobby. The button moves one seat to the left every hand, skipping players who cannot afford to play. With blinds, the two players after the button post the small and big blind and the player after the big blind acts first before the flop. Heads-up the button posts the small blind and acts first, so the big blind always has the last word before the flop.
+use std::fmt;
+use std::str::FromStr;
+
+/// The ante taken from every player when a lobby does not say otherwise.
+pub const DEFAULT_ANTE: i32 = 10;
+/// The small blind used when blinds are requested without amounts.
+pub const DEFAULT_SMALL_BLIND: i32 = 5;
+/// The big blind used when blinds are requested without amounts.
+pub const DEFAULT_BIG_BLIND: i32 = 10;
+
+/// The most bets and raises allowed in one betting round of a fixed-limit game.
+pub const FIXED_LIMIT_RAISE_CAP: usize = 4;
+
+/// The chips that go into the pot before any cards are dealt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForcedBets {
+    /// Every player pays the same ante into the pot.
+    Ante(i32),
+    /// The two players after the button post a small and a big blind, which count towards their first bet.
+    Blinds { small: i32, big: i32 },
+}
+
+impl ForcedBets {
+    /// The smallest opening bet, and the smallest raise, allowed in a betting round.
+    pub fn min_bet(&self) -> i32 {
+        match self {
+            ForcedBets::Ante(ante) => *ante,
+            ForcedBets::Blinds { big, .. } => *big,
+        }
+    }
+}
+
+impl fmt::Display for ForcedBets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ForcedBets::Ante(ante) => write!(f, "ante {}", ante),
+            ForcedBets::Blinds { small, big } => write!(f, "blinds {}/{}", small, big),
+        }
+    }
+}
+
+impl FromStr for ForcedBets {
+    type Err = String;
+
+    /// Parses the stakes typed when creating a lobby: `ante`, `ante=N`, `blinds` or `blinds=S/B`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid stakes '{}'. Use ante, ante=N, blinds or blinds=S/B.", s);
+        let (kind, amounts) = match s.split_once('=') {
+            Some((kind, amounts)) => (kind, Some(amounts)),
+            None => (s, None),
+        };
+        match (kind, amounts) {
+            ("ante", None) => Ok(ForcedBets::Ante(DEFAULT_ANTE)),
+            ("ante", Some(amount)) => match amount.parse::<i32>() {
+                Ok(ante) if ante > 0 => Ok(ForcedBets::Ante(ante)),
+                _ => Err(invalid()),
+            },
+            ("blinds", None) => Ok(ForcedBets::Blinds { small: DEFAULT_SMALL_BLIND, big: DEFAULT_BIG_BLIND }),
+            ("blinds", Some(amounts)) => {
+                let (small, big) = amounts.split_once('/').ok_or_else(invalid)?;
+                match (small.parse::<i32>(), big.parse::<i32>()) {
+                    (Ok(small), Ok(big)) if small > 0 && big >= small => Ok(ForcedBets::Blinds { small, big }),
+                    _ => Err(invalid()),
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// How much a player may bet or raise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BetLimit {
+    /// Any raise from the minimum up to the player's whole wallet.
+    NoLimit,
+    /// Any raise from the minimum up to the size of the pot after calling.
+    PotLimit,
+    /// Bets and raises of exactly the small bet in the first half of the betting rounds and the big bet after that, capped at `FIXED_LIMIT_RAISE_CAP` per round.
+    Fixed { small_bet: i32, big_bet: i32 },
+}
+
+/// The state of a betting round needed to check a raise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RaiseState {
+    /// The highest bet made so far this round.
+    pub table_bet: i32,
+    /// What the raising player has already bet this round.
+    pub player_bet: i32,
+    /// What the raising player has left in their wallet.
+    pub wallet: i32,
+    /// Every chip in the middle, including bets made this round.
+    pub pot: i32,
+    /// The smallest raise allowed: the size of the previous raise, or the minimum bet if nobody has raised.
+    pub min_raise: i32,
+    /// The number of bets and raises made so far this round.
+    pub raises: usize,
+    /// The index of this betting round within the hand.
+    pub round: usize,
+    /// The number of betting rounds in the hand.
+    pub rounds: usize,
+}
+
+impl BetLimit {
+    /// Parses the betting limit typed when creating a lobby: `nolimit`, `potlimit`, `fixed` or `fixed=S/B`.
+    ///
+    /// # Arguments
+    /// * `s` - The limit typed by the player.
+    /// * `forced_bets` - The lobby's forced bets. A plain `fixed` uses their minimum bet as the small bet and twice that as the big bet.
+    ///
+    /// # Returns
+    /// * `Ok(BetLimit)` - The parsed limit.
+    /// * `Err(String)` - A message explaining the accepted formats.
+    pub fn parse(s: &str, forced_bets: ForcedBets) -> Result<Self, String> {
+        let invalid = || format!("Invalid betting limit '{}'. Use nolimit, potlimit, fixed or fixed=S/B.", s);
+        match s.split_once('=') {
+            None => match s {
+                "nolimit" => Ok(BetLimit::NoLimit),
+                "potlimit" => Ok(BetLimit::PotLimit),
+                "fixed" => Ok(BetLimit::Fixed { small_bet: forced_bets.min_bet(), big_bet: 2 * forced_bets.min_bet() }),
+                _ => Err(invalid()),
+            },
+            Some(("fixed", sizes)) => {
+                let (small, big) = sizes.split_once('/').ok_or_else(invalid)?;
+                match (small.parse::<i32>(), big.parse::<i32>()) {
+                    (Ok(small_bet), Ok(big_bet)) if small_bet > 0 && big_bet >= small_bet => Ok(BetLimit::Fixed { small_bet, big_bet }),
+                    _ => Err(invalid()),
+                }
+            }
+            Some(_) => Err(invalid()),
+        }
+    }
+
+    /// The range of totals a player may raise their bet to this round.
+    ///
+    /// A player who cannot afford the minimum raise may still go all-in for less.
+    ///
+    /// # Arguments
+    /// * `state` - The state of the betting round.
+    ///
+    /// # Returns
+    /// * `Some((i32, i32))` - The smallest and largest total the player may raise to.
+    /// * `None` - If the player may not raise at all.
+    pub fn raise_range(&self, state: &RaiseState) -> Option<(i32, i32)> {
+        let all_in = state.player_bet + state.wallet;
+        if all_in <= state.table_bet {
+            return None;
+        }
+        let (min_to, max_to) = match *self {
+            BetLimit::NoLimit => (state.table_bet + state.min_raise, all_in),
+            BetLimit::PotLimit => {
+                // the pot after calling, which is also the largest raise on top of the call
+                let call = state.table_bet - state.player_bet;
+                (state.table_bet + state.min_raise, state.table_bet + state.pot + call)
+            }
+            BetLimit::Fixed { small_bet, big_bet } => {
+                if state.raises >= FIXED_LIMIT_RAISE_CAP {
+                    return None;
+                }
+                let size = if state.round * 2 < state.rounds { small_bet } else { big_bet };
+                (state.table_bet + size, state.table_bet + size)
+            }
+        };
+        Some((min_to.min(all_in), max_to.min(all_in)))
+    }
+
+    /// Checks a raise against the limit.
+    ///
+    /// # Arguments
+    /// * `state` - The state of the betting round.
+    /// * `raise_to` - The total the player wants their bet this round to be.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the raise is allowed.
+    /// * `Err(String)` - A message telling the player why the raise was rejected.
+    pub fn validate_raise(&self, state: &RaiseState, raise_to: i32) -> Result<(), String> {
+        let Some((min_to, max_to)) = self.raise_range(state) else {
+            if state.player_bet + state.wallet <= state.table_bet {
+                return Err("Invalid raise: not enough cash to raise.\nCall or fold.".to_string());
+            }
+            return Err(format!("Invalid raise: betting is capped at {} bets and raises per round.\nCall or fold.", FIXED_LIMIT_RAISE_CAP));
+        };
+        if (min_to..=max_to).contains(&raise_to) {
+            return Ok(());
+        }
+        Err(match self {
+            BetLimit::Fixed { .. } => format!("Invalid raise: this is a fixed-limit game, raise to exactly {}.", min_to),
+            _ if raise_to < min_to => format!("Invalid raise: the minimum raise is to {}.", min_to),
+            BetLimit::PotLimit => format!("Invalid raise: the pot limit allows a raise to at most {}.", max_to),
+            BetLimit::NoLimit => format!("Invalid raise: not enough cash, you can raise to at most {}.", max_to),
+        })
+    }
+}
+
+impl fmt::Display for BetLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BetLimit::NoLimit => write!(f, "no-limit"),
+            BetLimit::PotLimit => write!(f, "pot-limit"),
+            BetLimit::Fixed { small_bet, big_bet } => write!(f, "fixed-limit {}/{}", small_bet, big_bet),
+        }
+    }
+}
+
+/// Where the button and the blinds sit for a hand, as seat indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Positions {
+    pub button: usize,
+    pub small_blind: usize,
+    pub big_blind: usize,
+    /// The first player to act in the opening betting round.
+    pub first_to_act: usize,
+}
+
+/// Finds the next seat after `seat` whose player is taking part in the hand.
+///
+/// # Arguments
+/// * `seat` - The seat to start from. It may be `-1` to start before the first seat.
+/// * `active` - Whether the player in each seat is taking part in the hand.
+///
+/// # Returns
+/// * `Option<usize>` - The next active seat, wrapping around the table, or `None` if nobody is active.
+pub fn next_active_seat(seat: i32, active: &[bool]) -> Option<usize> {
+    let seat_count = active.len() as i32;
+    (1..=seat_count)
+        .map(|offset| (seat + offset).rem_euclid(seat_count) as usize)
+        .find(|&next| active[next])
+}
+
+/// Moves the button on from the previous hand and places the blinds.
+///
+/// # Arguments
+/// * `previous_button` - The button seat of the previous hand, or `-1` before the first hand.
+/// * `active` - Whether the player in each seat is taking part in the hand.
+///
+/// # Returns
+/// * `Option<Positions>` - The positions for the new hand, or `None` if fewer than two players are active.
+pub fn next_positions(previous_button: i32, active: &[bool]) -> Option<Positions> {
+    if active.iter().filter(|&&a| a).count() < 2 {
+        return None;
+    }
+    let button = next_active_seat(previous_button, active)?;
+    let after_button = next_active_seat(button as i32, active)?;
+    let heads_up = active.iter().filter(|&&a| a).count() == 2;
+    let small_blind = if heads_up { button } else { after_button };
+    let big_blind = next_active_seat(small_blind as i32, active)?;
+    let first_to_act = next_active_seat(big_blind as i32, active)?;
+    Some(Positions { button, small_blind, big_blind, first_to_act })
+}
+
+/// An action a player can take on their turn in a betting round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Fold,
+    Check,
+    Call,
+    /// Open the betting, making the player's bet this round the given total.
+    Bet(i32),
+    /// Raise the bet, making the player's bet this round the given total.
+    RaiseTo(i32),
+    /// Put the player's whole wallet in.
+    AllIn,
+}
+
+/// Something that happened in a betting round, for the lobby to apply and announce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Folded { seat: usize },
+    Checked { seat: usize },
+    /// The player matched the bet by adding `amount` chips.
+    Called { seat: usize, amount: i32 },
+    /// The player opened the betting with a bet of `to`.
+    Bet { seat: usize, to: i32 },
+    /// The player raised their bet this round to `to`.
+    RaisedTo { seat: usize, to: i32 },
+    /// The player put their whole wallet in, making their bet this round `to`.
+    AllIn { seat: usize, to: i32 },
+    /// Everyone else folded, so this player wins the hand without a showdown.
+    HandWon { seat: usize },
+    /// Every player still in the hand has matched the bet or is all-in.
+    RoundComplete,
+}
+
+/// A player's place in a betting round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seat {
+    pub wallet: i32,
+    /// What the player has bet so far this round.
+    pub bet: i32,
+    pub folded: bool,
+    pub all_in: bool,
+    /// Whether the player has acted since the betting was last opened or raised.
+    acted: bool,
+}
+
+impl Seat {
+    /// Creates a seat for a player with the given wallet and bet, such as a posted blind.
+    ///
+    /// A player still in the hand with an empty wallet is all-in.
+    pub fn new(wallet: i32, bet: i32, folded: bool) -> Self {
+        Seat { wallet, bet, folded, all_in: !folded && wallet == 0, acted: false }
+    }
+
+    fn can_act(&self) -> bool {
+        !self.folded && !self.all_in
+    }
+}
+
+/// A single betting round, with no I/O: the lobby feeds it each player's action and applies the events it returns.
+///
+/// Turn order moves left around the table, skipping players who have folded or are all-in. The round ends once every player who can still act has acted since the last full bet or raise and matched it, or when only one player is left in the hand.
+#[derive(Debug, Clone)]
+pub struct BettingRound {
+    seats: Vec<Seat>,
+    limit: BetLimit,
+    pot: i32,
+    table_bet: i32,
+    min_raise: i32,
+    raises: usize,
+    round: usize,
+    rounds: usize,
+    to_act: Option<usize>,
+}
+
+impl BettingRound {
+    /// Starts a betting round.
+    ///
+    /// # Arguments
+    /// * `seats` - Every seat at the table, in order.
+    /// * `first_to_act` - The seat to start from. If that player cannot act, the turn passes to the next one who can.
+    /// * `pot` - Every chip already in the middle, including any bets in `seats`.
+    /// * `min_bet` - The smallest opening bet and the smallest raise.
+    /// * `limit` - The betting limit of the lobby.
+    /// * `round` - The index of this betting round within the hand.
+    /// * `rounds` - The number of betting rounds in the hand.
+    pub fn new(seats: Vec<Seat>, first_to_act: usize, pot: i32, min_bet: i32, limit: BetLimit, round: usize, rounds: usize) -> Self {
+        let table_bet = seats.iter().map(|seat| seat.bet).max().unwrap_or(0);
+        let mut betting_round = BettingRound {
+            seats,
+            limit,
+            pot,
+            table_bet,
+            min_raise: min_bet,
+            // a posted big blind counts as the first bet of the round
+            raises: if table_bet > 0 { 1 } else { 0 },
+            round,
+            rounds,
+            to_act: None,
+        };
+        betting_round.to_act = betting_round.next_to_act(first_to_act);
+        betting_round
+    }
+
+    /// The seat whose turn it is, or `None` once the round is over.
+    pub fn to_act(&self) -> Option<usize> {
+        self.to_act
+    }
+
+    pub fn seat(&self, seat: usize) -> &Seat {
+        &self.seats[seat]
+    }
+
+    /// Every chip in the middle, including the bets made this round.
+    pub fn pot(&self) -> i32 {
+        self.pot
+    }
+
+    /// The highest bet made so far this round.
+    pub fn table_bet(&self) -> i32 {
+        self.table_bet
+    }
+
+    /// The number of players who have not folded.
+    pub fn players_in_hand(&self) -> usize {
+        self.seats.iter().filter(|seat| !seat.folded).count()
+    }
+
+    /// What the player in `seat` has to add to match the bet.
+    pub fn call_amount(&self, seat: usize) -> i32 {
+        self.table_bet - self.seats[seat].bet
+    }
+
+    /// The range of totals the player in `seat` may bet or raise to, or `None` if they may not raise.
+    ///
+    /// A player who has already acted can only be facing a short all-in, which does not reopen the betting, so they may not raise.
+    pub fn raise_range(&self, seat: usize) -> Option<(i32, i32)> {
+        if self.seats[seat].acted {
+            return None;
+        }
+        self.limit.raise_range(&self.raise_state(seat))
+    }
+
+    /// Checks whether the player in `seat` may bet or raise to `to`, without changing the round.
+    pub fn check_raise(&self, seat: usize, to: i32) -> Result<(), String> {
+        if self.seats[seat].acted {
+            return Err("Invalid move: a short all-in does not reopen the betting, you can only call or fold.".to_string());
+        }
+        self.limit.validate_raise(&self.raise_state(seat), to)
+    }
+
+    /// Applies a player's action.
+    ///
+    /// # Arguments
+    /// * `seat` - The seat of the player acting.
+    /// * `action` - What they want to do.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<Event>)` - What happened, ending with `RoundComplete` or `HandWon` if the round is over.
+    /// * `Err(String)` - A message for the player if the action is not allowed. The round is left unchanged.
+    pub fn apply(&mut self, seat: usize, action: Action) -> Result<Vec<Event>, String> {
+        if self.to_act != Some(seat) {
+            return Err("Invalid move: it's not your turn.".to_string());
+        }
+        let call = self.call_amount(seat);
+        let wallet = self.seats[seat].wallet;
+        let event = match action {
+            Action::Fold => {
+                self.seats[seat].folded = true;
+                Event::Folded { seat }
+            }
+            Action::Check => {
+                if call > 0 {
+                    return Err("Invalid move: You can't check, there's a bet to call.".to_string());
+                }
+                Event::Checked { seat }
+            }
+            Action::Call => {
+                if call == 0 {
+                    return Err("Invalid move: no bet to call.".to_string());
+                }
+                if call > wallet {
+                    return Err("Invalid move: not enough cash.\nAll in or fold!".to_string());
+                }
+                self.put_in(seat, call);
+                Event::Called { seat, amount: call }
+            }
+            Action::Bet(to) => {
+                if self.table_bet > 0 {
+                    return Err("Invalid move: there's already a bet, raise instead.".to_string());
+                }
+                self.raise_to(seat, to)?;
+                Event::Bet { seat, to }
+            }
+            Action::RaiseTo(to) => {
+                if self.table_bet == 0 {
+                    return Err("Invalid move: there's no bet to raise, bet instead.".to_string());
+                }
+                self.raise_to(seat, to)?;
+                Event::RaisedTo { seat, to }
+            }
+            Action::AllIn => {
+                if wallet == 0 {
+                    return Err("Invalid move: you have no chips left.".to_string());
+                }
+                let to = self.seats[seat].bet + wallet;
+                if to > self.table_bet {
+                    // going all-in for more than the bet is a raise, so it must respect the limit
+                    self.raise_to(seat, to).map_err(|message| format!("{}\nYou can't go all-in here.", message))?;
+                } else {
+                    self.put_in(seat, wallet);
+                }
+                Event::AllIn { seat, to }
+            }
+        };
+
+        let player = &mut self.seats[seat];
+        player.acted = true;
+        if !player.folded && player.wallet == 0 {
+            player.all_in = true;
+        }
+        let mut events = vec![event];
+        if self.players_in_hand() == 1 {
+            self.to_act = None;
+            if let Some(winner) = self.seats.iter().position(|seat| !seat.folded) {
+                events.push(Event::HandWon { seat: winner });
+            }
+        } else {
+            self.to_act = self.next_to_act(seat + 1);
+            if self.to_act.is_none() {
+                events.push(Event::RoundComplete);
+            }
+        }
+        Ok(events)
+    }
+
+    fn raise_state(&self, seat: usize) -> RaiseState {
+        RaiseState {
+            table_bet: self.table_bet,
+            player_bet: self.seats[seat].bet,
+            wallet: self.seats[seat].wallet,
+            pot: self.pot,
+            min_raise: self.min_raise,
+            raises: self.raises,
+            round: self.round,
+            rounds: self.rounds,
+        }
+    }
+
+    fn raise_to(&mut self, seat: usize, to: i32) -> Result<(), String> {
+        self.check_raise(seat, to)?;
+        // only a full raise reopens the betting, a short all-in just has to be called
+        let all_in = to == self.seats[seat].bet + self.seats[seat].wallet;
+        if !all_in || to - self.table_bet >= self.min_raise {
+            self.min_raise = to - self.table_bet;
+            self.raises += 1;
+            for other in self.seats.iter_mut() {
+                other.acted = false;
+            }
+        }
+        self.table_bet = to;
+        let amount = to - self.seats[seat].bet;
+        self.put_in(seat, amount);
+        Ok(())
+    }
+
+    fn put_in(&mut self, seat: usize, amount: i32) {
+        self.seats[seat].wallet -= amount;
+        self.seats[seat].bet += amount;
+        self.pot += amount;
+    }
+
+    /// Finds the next player who still has to act, starting from `start` and moving around the table.
+    fn next_to_act(&self, start: usize) -> Option<usize> {
+        if self.players_in_hand() <= 1 {
+            return None;
+        }
+        let can_act: Vec<usize> = (0..self.seats.len()).filter(|&i| self.seats[i].can_act()).collect();
+        // a lone player who has matched every all-in has nobody left to bet against
+        if can_act.len() == 1 && self.seats[can_act[0]].bet >= self.table_bet {
+            return None;
+        }
+        (0..self.seats.len())
+            .map(|offset| (start + offset) % self.seats.len())
+            .find(|&i| {
+                let seat = &self.seats[i];
+                seat.can_act() && (!seat.acted || seat.bet < self.table_bet)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forced_bets() {
+        assert_eq!("ante".parse(), Ok(ForcedBets::Ante(DEFAULT_ANTE)));
+        assert_eq!("ante=25".parse(), Ok(ForcedBets::Ante(25)));
+        assert_eq!("blinds".parse(), Ok(ForcedBets::Blinds { small: 5, big: 10 }));
+        assert_eq!("blinds=10/20".parse(), Ok(ForcedBets::Blinds { small: 10, big: 20 }));
+        for invalid in ["ante=0", "ante=x", "blinds=20/10", "blinds=10", "straddle"] {
+            assert!(invalid.parse::<ForcedBets>().is_err(), "{} should not parse", invalid);
+        }
+        assert_eq!(ForcedBets::Blinds { small: 10, big: 20 }.to_string(), "blinds 10/20");
+    }
+
+    fn raise_state(table_bet: i32, player_bet: i32, wallet: i32, pot: i32) -> RaiseState {
+        RaiseState { table_bet, player_bet, wallet, pot, min_raise: 10, raises: 1, round: 0, rounds: 4 }
+    }
+
+    #[test]
+    fn test_parse_bet_limit() {
+        let blinds = ForcedBets::Blinds { small: 5, big: 10 };
+        assert_eq!(BetLimit::parse("nolimit", blinds), Ok(BetLimit::NoLimit));
+        assert_eq!(BetLimit::parse("potlimit", blinds), Ok(BetLimit::PotLimit));
+        assert_eq!(BetLimit::parse("fixed", blinds), Ok(BetLimit::Fixed { small_bet: 10, big_bet: 20 }));
+        assert_eq!(BetLimit::parse("fixed=20/40", blinds), Ok(BetLimit::Fixed { small_bet: 20, big_bet: 40 }));
+        assert!(BetLimit::parse("fixed=40/20", blinds).is_err());
+        assert!(BetLimit::parse("spread", blinds).is_err());
+    }
+
+    #[test]
+    fn test_no_limit_minimum_raise() {
+        // the big blind of 10 was raised to 30, so the next raise must be to at least 50
+        let state = RaiseState { min_raise: 20, ..raise_state(30, 10, 500, 45) };
+        assert_eq!(BetLimit::NoLimit.raise_range(&state), Some((50, 510)));
+        assert!(BetLimit::NoLimit.validate_raise(&state, 49).is_err());
+        assert!(BetLimit::NoLimit.validate_raise(&state, 50).is_ok());
+        assert!(BetLimit::NoLimit.validate_raise(&state, 510).is_ok());
+        assert!(BetLimit::NoLimit.validate_raise(&state, 511).is_err());
+    }
+
+    #[test]
+    fn test_short_stack_may_raise_all_in() {
+        let state = raise_state(30, 0, 35, 45);
+        assert_eq!(BetLimit::NoLimit.raise_range(&state), Some((35, 35)));
+        assert!(BetLimit::NoLimit.raise_range(&raise_state(30, 0, 30, 45)).is_none());
+    }
+
+    #[test]
+    fn test_pot_limit_maximum() {
+        // 60 in the pot including a bet of 20: calling makes it 80, so the most is a raise to 100
+        let state = raise_state(20, 0, 500, 60);
+        assert_eq!(BetLimit::PotLimit.raise_range(&state), Some((30, 100)));
+        assert!(BetLimit::PotLimit.validate_raise(&state, 100).is_ok());
+        let message = BetLimit::PotLimit.validate_raise(&state, 101).unwrap_err();
+        assert!(message.contains("pot limit"), "{}", message);
+    }
+
+    #[test]
+    fn test_fixed_limit_sizes_and_cap() {
+        let limit = BetLimit::Fixed { small_bet: 10, big_bet: 20 };
+        let early = raise_state(10, 0, 500, 15);
+        assert_eq!(limit.raise_range(&early), Some((20, 20)));
+        let late = RaiseState { round: 2, ..raise_state(20, 0, 500, 100) };
+        assert_eq!(limit.raise_range(&late), Some((40, 40)));
+        assert!(limit.validate_raise(&late, 60).is_err());
+
+        let capped = RaiseState { raises: FIXED_LIMIT_RAISE_CAP, ..late };
+        let message = limit.validate_raise(&capped, 40).unwrap_err();
+        assert!(message.contains("capped"), "{}", message);
+    }
+
+    #[test]
+    fn test_button_rotates_around_the_table() {
+        let active = [true; 4];
+        let mut button = -1;
+        let mut buttons = Vec::new();
+        for _ in 0..5 {
+            let positions = next_positions(button, &active).unwrap();
+            buttons.push(positions.button);
+            button = positions.button as i32;
+        }
+        assert_eq!(buttons, vec![0, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_blind_positions() {
+        let positions = next_positions(0, &[true; 4]).unwrap();
+        assert_eq!(positions, Positions { button: 1, small_blind: 2, big_blind: 3, first_to_act: 0 });
+
+        // three handed the button acts first before the flop
+        let positions = next_positions(-1, &[true; 3]).unwrap();
+        assert_eq!(positions, Positions { button: 0, small_blind: 1, big_blind: 2, first_to_act: 0 });
+    }
+
+    #[test]
+    fn test_heads_up_button_posts_small_blind() {
+        let positions = next_positions(1, &[true, true]).unwrap();
+        assert_eq!(positions, Positions { button: 0, small_blind: 0, big_blind: 1, first_to_act: 0 });
+    }
+
+    #[test]
+    fn test_inactive_seats_are_skipped() {
+        // seat 1 cannot afford to play, so the button and blinds pass over it
+        let active = [true, false, true, true, true];
+        let positions = next_positions(0, &active).unwrap();
+        assert_eq!(positions, Positions { button: 2, small_blind: 3, big_blind: 4, first_to_act: 0 });
+        assert_eq!(next_active_seat(4, &active), Some(0));
+        assert!(next_positions(0, &[true, false, false]).is_none());
+    }
+
+    /// Three players with 100 each, the small and big blind of 5 and 10 already posted by seats 1 and 2.
+    fn blinds_round(limit: BetLimit) -> BettingRound {
+        let seats = vec![Seat::new(100, 0, false), Seat::new(95, 5, false), Seat::new(90, 10, false)];
+        BettingRound::new(seats, 0, 15, 10, limit, 0, 4)
+    }
+
+    #[test]
+    fn test_big_blind_gets_the_option() {
+        let mut round = blinds_round(BetLimit::NoLimit);
+        assert_eq!(round.to_act(), Some(0));
+        assert_eq!(round.apply(0, Action::Call), Ok(vec![Event::Called { seat: 0, amount: 10 }]));
+        assert_eq!(round.apply(1, Action::Call), Ok(vec![Event::Called { seat: 1, amount: 5 }]));
+        // everyone called, but the big blind still gets to act
+        assert_eq!(round.to_act(), Some(2));
+        assert_eq!(round.apply(2, Action::Check), Ok(vec![Event::Checked { seat: 2 }, Event::RoundComplete]));
+        assert_eq!(round.to_act(), None);
+        assert_eq!(round.pot(), 30);
+    }
+
+    #[test]
+    fn test_raise_is_a_total_to_reach() {
+        let mut round = blinds_round(BetLimit::NoLimit);
+        round.apply(0, Action::RaiseTo(30)).unwrap();
+        // the small blind already has 5 in, so raising to 60 adds 55
+        round.apply(1, Action::RaiseTo(60)).unwrap();
+        assert_eq!(round.seat(1).bet, 60);
+        assert_eq!(round.seat(1).wallet, 40);
+        // the last raise was 30, so the next must be to at least 90
+        assert_eq!(round.raise_range(2), Some((90, 100)));
+        assert!(round.apply(2, Action::RaiseTo(80)).is_err());
+    }
+
+    #[test]
+    fn test_raise_reopens_the_betting() {
+        let mut round = blinds_round(BetLimit::NoLimit);
+        round.apply(0, Action::Call).unwrap();
+        round.apply(1, Action::Call).unwrap();
+        round.apply(2, Action::RaiseTo(40)).unwrap();
+        assert_eq!(round.to_act(), Some(0));
+        round.apply(0, Action::Fold).unwrap();
+        assert_eq!(round.apply(1, Action::Call), Ok(vec![Event::Called { seat: 1, amount: 30 }, Event::RoundComplete]));
+    }
+
+    #[test]
+    fn test_folded_and_all_in_players_are_skipped() {
+        let seats = vec![
+            Seat::new(100, 0, false),
+            Seat::new(100, 0, true),
+            Seat::new(0, 0, false),
+            Seat::new(100, 0, false),
+        ];
+        let mut round = BettingRound::new(seats, 1, 40, 10, BetLimit::NoLimit, 1, 4);
+        assert_eq!(round.to_act(), Some(3));
+        round.apply(3, Action::Check).unwrap();
+        assert_eq!(round.to_act(), Some(0));
+        assert_eq!(round.apply(0, Action::Check), Ok(vec![Event::Checked { seat: 0 }, Event::RoundComplete]));
+    }
+
+    #[test]
+    fn test_everyone_folds_to_a_bet() {
+        let mut round = blinds_round(BetLimit::NoLimit);
+        round.apply(0, Action::Fold).unwrap();
+        assert_eq!(round.apply(1, Action::Fold), Ok(vec![Event::Folded { seat: 1 }, Event::HandWon { seat: 2 }]));
+        assert_eq!(round.to_act(), None);
+        assert_eq!(round.players_in_hand(), 1);
+    }
+
+    #[test]
+    fn test_invalid_actions_are_rejected() {
+        let mut round = blinds_round(BetLimit::NoLimit);
+        assert!(round.apply(1, Action::Call).is_err(), "acting out of turn");
+        assert!(round.apply(0, Action::Check).is_err(), "checking facing a bet");
+        assert!(round.apply(0, Action::Bet(20)).is_err(), "betting when there is already a bet");
+        assert!(round.apply(0, Action::RaiseTo(15)).is_err(), "raising less than the minimum");
+        assert!(round.apply(0, Action::RaiseTo(101)).is_err(), "raising more than the wallet");
+        assert_eq!(round.to_act(), Some(0));
+        assert_eq!(round.pot(), 15);
+        assert_eq!(round.seat(0).wallet, 100);
+    }
+
+    #[test]
+    fn test_all_in_for_less_than_a_call() {
+        let seats = vec![Seat::new(100, 0, false), Seat::new(25, 0, false)];
+        let mut round = BettingRound::new(seats, 0, 20, 10, BetLimit::NoLimit, 0, 2);
+        round.apply(0, Action::Bet(50)).unwrap();
+        assert!(round.apply(1, Action::Call).is_err());
+        assert_eq!(round.apply(1, Action::AllIn), Ok(vec![Event::AllIn { seat: 1, to: 25 }, Event::RoundComplete]));
+        assert!(round.seat(1).all_in);
+    }
+
+    #[test]
+    fn test_short_all_in_does_not_reopen_the_betting() {
+        let seats = vec![Seat::new(200, 0, false), Seat::new(60, 0, false)];
+        let mut round = BettingRound::new(seats, 0, 20, 10, BetLimit::NoLimit, 1, 4);
+        round.apply(0, Action::Bet(50)).unwrap();
+        // a full raise would have to be to at least 100
+        round.apply(1, Action::AllIn).unwrap();
+        assert_eq!(round.to_act(), Some(0));
+        assert_eq!(round.raise_range(0), None);
+        assert!(round.check_raise(0, 120).is_err());
+        assert!(round.apply(0, Action::RaiseTo(120)).is_err());
+        assert!(round.apply(0, Action::AllIn).is_err());
+        assert_eq!(round.apply(0, Action::Call), Ok(vec![Event::Called { seat: 0, amount: 10 }, Event::RoundComplete]));
+    }
+
+    #[test]
+    fn test_pot_limit_all_in_is_capped() {
+        let seats = vec![Seat::new(1000, 0, false), Seat::new(1000, 0, false)];
+        let mut round = BettingRound::new(seats, 0, 20, 10, BetLimit::PotLimit, 1, 4);
+        let message = round.apply(0, Action::AllIn).unwrap_err();
+        assert!(message.contains("pot limit"), "{}", message);
+        round.apply(0, Action::Bet(20)).unwrap();
+        assert_eq!(round.raise_range(1), Some((40, 80)));
+    }
+
+    /// Picks an action from two random numbers, so the property tests cover both legal and illegal moves.
+    fn pick_action(kind: u8, amount: i32) -> Action {
+        match kind % 6 {
+            0 => Action::Fold,
+            1 => Action::Check,
+            2 => Action::Call,
+            3 => Action::Bet(amount),
+            4 => Action::RaiseTo(amount),
+            _ => Action::AllIn,
+        }
+    }
+
+    fn chips(round: &BettingRound, seat_count: usize) -> i32 {
+        (0..seat_count).map(|i| round.seat(i).wallet).sum::<i32>() + round.pot()
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_betting_round_invariants(
+            wallets in proptest::collection::vec(0..300i32, 2..7),
+            first_to_act in 0..6usize,
+            limit in 0..3u8,
+            actions in proptest::collection::vec((0..6u8, 0..400i32), 0..60),
+        ) {
+            let seat_count = wallets.len();
+            let limit = match limit {
+                0 => BetLimit::NoLimit,
+                1 => BetLimit::PotLimit,
+                _ => BetLimit::Fixed { small_bet: 10, big_bet: 20 },
+            };
+            let seats = wallets.iter().map(|&wallet| Seat::new(wallet, 0, false)).collect();
+            let mut round = BettingRound::new(seats, first_to_act % seat_count, 0, 10, limit, 0, 2);
+            let total = chips(&round, seat_count);
+
+            for (kind, amount) in actions {
+                let Some(seat) = round.to_act() else { break };
+                let before = round.clone();
+                match round.apply(seat, pick_action(kind, amount)) {
+                    Ok(events) => proptest::prop_assert!(!events.is_empty()),
+                    Err(_) => {
+                        // a rejected action changes nothing
+                        proptest::prop_assert_eq!(round.pot(), before.pot());
+                        proptest::prop_assert_eq!(round.to_act(), before.to_act());
+                    }
+                }
+                proptest::prop_assert_eq!(chips(&round, seat_count), total);
+                if let Some(next) = round.to_act() {
+                    proptest::prop_assert!(!round.seat(next).folded && !round.seat(next).all_in);
+                }
+            }
+
+            // finishing by calling or checking always ends the round
+            let mut steps = 0;
+            while let Some(seat) = round.to_act() {
+                let action = if round.call_amount(seat) == 0 {
+                    Action::Check
+                } else if round.call_amount(seat) < round.seat(seat).wallet {
+                    Action::Call
+                } else {
+                    Action::AllIn
+                };
+                round.apply(seat, action).unwrap();
+                steps += 1;
+                proptest::prop_assert!(steps <= seat_count);
+            }
+
+            proptest::prop_assert_eq!(chips(&round, seat_count), total);
+            for i in 0..seat_count {
+                let seat = round.seat(i);
+                proptest::prop_assert!(seat.wallet >= 0);
+                // everyone still able to bet has matched the highest bet
+                if round.players_in_hand() > 1 && !seat.folded && !seat.all_in {
+                    proptest::prop_assert_eq!(seat.bet, round.table_bet());
+                }
+            }
+        }
+    }
+}
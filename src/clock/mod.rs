@@ -0,0 +1,143 @@
+//! A module for the action clock that limits how long a player can take on their turn
+//!
+//! Each lobby can give players a fixed number of seconds to act, with countdown warnings sent as the time runs down. A lobby can also give every player a time bank: extra seconds they get once per hand when their clock runs out. If both run out the lobby acts for the player, checking if it can and folding otherwise.
+use std::fmt;
+use tokio::time::{Duration, Instant};
+
+/// The seconds a player has to act when a lobby does not say otherwise.
+pub const DEFAULT_ACTION_SECONDS: u64 = 30;
+
+/// Players are warned when this many seconds are left on their clock.
+pub const COUNTDOWN_WARNINGS: [u64; 3] = [20, 10, 5];
+
+/// How long players in a lobby have to act.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionClock {
+    pub seconds: u64,
+    /// Extra seconds each player can draw on once per hand, 0 for no time bank.
+    pub time_bank: u64,
+}
+
+impl Default for ActionClock {
+    fn default() -> Self {
+        ActionClock { seconds: DEFAULT_ACTION_SECONDS, time_bank: 0 }
+    }
+}
+
+impl fmt::Display for ActionClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.time_bank > 0 {
+            write!(f, "{}s clock + {}s bank", self.seconds, self.time_bank)
+        } else {
+            write!(f, "{}s clock", self.seconds)
+        }
+    }
+}
+
+/// Parses the action clock typed when creating a lobby: `clock=SECONDS` or `clock=SECONDS+BANK`.
+///
+/// `clock=off` parses to `None`, a lobby that waits for players forever.
+pub fn parse_action_clock(s: &str) -> Result<Option<ActionClock>, String> {
+    let invalid = || format!("Invalid action clock '{}'. Use clock=SECONDS, clock=SECONDS+BANK or clock=off.", s);
+    let setting = s.strip_prefix("clock=").ok_or_else(invalid)?;
+    if setting == "off" {
+        return Ok(None);
+    }
+    let (seconds, time_bank) = setting.split_once('+').unwrap_or((setting, "0"));
+    match (seconds.parse::<u64>(), time_bank.parse::<u64>()) {
+        (Ok(seconds), Ok(time_bank)) if seconds > 0 => Ok(Some(ActionClock { seconds, time_bank })),
+        _ => Err(invalid()),
+    }
+}
+
+/// The clock for one player's turn.
+#[derive(Debug, Clone, Copy)]
+pub struct Turn {
+    deadline: Option<Instant>,
+    time_bank: Duration,
+}
+
+impl Turn {
+    /// Starts the clock for a turn, or a turn without a time limit if the lobby has no clock.
+    pub fn start(clock: Option<ActionClock>) -> Self {
+        match clock {
+            Some(clock) => Turn {
+                deadline: Some(Instant::now() + Duration::from_secs(clock.seconds)),
+                time_bank: Duration::from_secs(clock.time_bank),
+            },
+            None => Turn { deadline: None, time_bank: Duration::ZERO },
+        }
+    }
+
+    /// When the player's time runs out, or `None` if there is no time limit.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Adds the time bank to the clock.
+    ///
+    /// # Returns
+    /// * `Some(u64)` - The extra seconds the player got.
+    /// * `None` - If the lobby has no time bank, or it was already added this turn.
+    pub fn use_time_bank(&mut self) -> Option<u64> {
+        if self.time_bank.is_zero() {
+            return None;
+        }
+        let deadline = self.deadline?;
+        self.deadline = Some(deadline.max(Instant::now()) + self.time_bank);
+        let seconds = self.time_bank.as_secs();
+        self.time_bank = Duration::ZERO;
+        Some(seconds)
+    }
+}
+
+/// Finds the next countdown warning to send.
+///
+/// # Arguments
+/// * `remaining` - The time left on the clock.
+///
+/// # Returns
+/// * `Option<u64>` - The largest warning, in seconds, that is still to come, or `None` if every warning has passed.
+pub fn next_warning(remaining: Duration) -> Option<u64> {
+    COUNTDOWN_WARNINGS
+        .iter()
+        .copied()
+        .find(|&seconds| Duration::from_secs(seconds) < remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_action_clock() {
+        assert_eq!(parse_action_clock("clock=45"), Ok(Some(ActionClock { seconds: 45, time_bank: 0 })));
+        assert_eq!(parse_action_clock("clock=20+60"), Ok(Some(ActionClock { seconds: 20, time_bank: 60 })));
+        assert_eq!(parse_action_clock("clock=off"), Ok(None));
+        for invalid in ["clock=0", "clock=ten", "clock=20+", "timer=20"] {
+            assert!(parse_action_clock(invalid).is_err(), "{} should not parse", invalid);
+        }
+        assert_eq!(ActionClock { seconds: 20, time_bank: 60 }.to_string(), "20s clock + 60s bank");
+    }
+
+    #[test]
+    fn test_next_warning() {
+        assert_eq!(next_warning(Duration::from_secs(30)), Some(20));
+        assert_eq!(next_warning(Duration::from_millis(19_900)), Some(10));
+        assert_eq!(next_warning(Duration::from_secs(10)), Some(5));
+        assert_eq!(next_warning(Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn test_time_bank_is_used_once() {
+        let mut turn = Turn::start(Some(ActionClock { seconds: 10, time_bank: 30 }));
+        let deadline = turn.deadline().unwrap();
+        assert_eq!(turn.use_time_bank(), Some(30));
+        assert_eq!(turn.deadline(), Some(deadline + Duration::from_secs(30)));
+        assert_eq!(turn.use_time_bank(), None);
+
+        let mut untimed = Turn::start(None);
+        assert_eq!(untimed.deadline(), None);
+        assert_eq!(untimed.use_time_bank(), None);
+    }
+}
@@ -1,18 +1,69 @@
 //! Database module to handle player registration, login, and player statistics using SQLite.
 //! 
 //! This module provides functionality for player management, including:
-//! - Registering new players with a unique ID and initial wallet balance.
-//! - Logging in players by their username.
+//! - Bringing an existing database up to date with the current schema at startup.
+//! - Registering new players with a unique ID, a salted password hash and initial wallet balance.
+//! - Logging in players by their username and password.
+//! - Issuing session tokens that let a player resume their session on a new connection.
 //! - Retrieving player statistics (games played, games won, wallet balance).
 //! - Updating player statistics after a game.
+//! - Recording the history of every game, numbered sequentially from 0, and each player's result in it.
+//! - Making players administrators, who can reset the game numbering and every player's stats.
+//! - Resetting the game numbering and every player's stats.
+//! - Ranking players on leaderboards by wallet, wins, win rate or net profit.
+//! - Recording every betting action, and deriving each player's VPIP, preflop raise rate, aggression and showdown stats per variant from them.
 //! 
-//! It uses `sqlx` for asynchronous database interactions and `uuid` for unique player IDs.
+//! It uses `sqlx` for asynchronous database interactions, `uuid` for unique player IDs and `argon2` for password hashing.
+//! 
+//! Session tokens are random and only their SHA-256 hash is stored, so a copy of the database cannot be used to take over a session.
 
+use crate::deck::to_hex;
 use crate::lobby::Player;
+use crate::protocol::{self, ActionKind, LeaderboardMetric};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::Rng;
+use sha2::{Digest, Sha256};
 use sqlx::{SqlitePool, Row};
 use uuid::Uuid;
 use std::sync::Arc;
 
+/// How long a session token can be used to resume a session, in seconds.
+pub const SESSION_DURATION_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Columns added to tables after they were first created, as (table, column, definition).
+///
+/// `CREATE TABLE IF NOT EXISTS` leaves an existing table as it is, so these are added to databases made before them.
+const ADDED_COLUMNS: [(&str, &str, &str); 5] = [
+    ("players", "password_hash", "TEXT"),
+    ("players", "is_admin", "INTEGER NOT NULL DEFAULT 0"),
+    ("games", "pot", "INTEGER NOT NULL DEFAULT 0"),
+    ("game_participants", "showdown", "INTEGER NOT NULL DEFAULT 0"),
+    ("game_participants", "collected", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+/// Hashes a password with a fresh random salt, in the PHC string format.
+fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("hashing with the default parameters cannot fail")
+        .to_string()
+}
+
+/// Checks a password against a hash made by `hash_password`.
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+/// The hash a session token is stored under.
+fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
 /// Represents a player's statistics, including games played, games won, and wallet balance.
 #[derive(Debug)]
 pub struct PlayerStats {
@@ -21,6 +72,182 @@ pub struct PlayerStats {
     pub games_played: i32,
     pub games_won: i32,
     pub wallet: i32,
+    /// Chips won or lost over every recorded game.
+    pub net_chips: i32,
+}
+
+impl PlayerStats {
+    /// The share of games played that the player won, from 0 to 1, or 0 if they have not played yet.
+    pub fn win_rate(&self) -> f64 {
+        if self.games_played == 0 {
+            0.0
+        } else {
+            self.games_won as f64 / self.games_played as f64
+        }
+    }
+}
+
+impl From<PlayerStats> for protocol::PlayerStats {
+    fn from(stats: PlayerStats) -> Self {
+        protocol::PlayerStats {
+            win_rate: stats.win_rate(),
+            net_chips: stats.net_chips,
+            id: stats.id,
+            name: stats.name,
+            games_played: stats.games_played,
+            games_won: stats.games_won,
+            wallet: stats.wallet,
+        }
+    }
+}
+
+/// The chips a newly registered player starts with, and what every wallet goes back to on a reset.
+pub const STARTING_WALLET: i32 = 1000;
+
+/// The sum of a player's results over every recorded game, as a column of a query on `players`.
+const NET_CHIPS: &str = "(SELECT COALESCE(SUM(net_result), 0) FROM game_participants WHERE game_participants.player_id = players.id)";
+
+/// One player's result in a finished game, as recorded by `finish_game`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameResult {
+    pub player_id: String,
+    /// Chips won or lost over the game.
+    pub net_result: i32,
+    /// The hand at the end of the game in short notation, or `None` if the player folded.
+    pub final_hand: Option<String>,
+    pub won: bool,
+    /// Whether the player was still in the hand when it was shown down.
+    pub showdown: bool,
+    /// Chips the player took from the pots they won, which is only their share of a split pot.
+    pub collected: i32,
+}
+
+/// A move made in a betting round, as recorded by `finish_game`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedAction {
+    pub player_id: String,
+    /// 0 for the first betting round, before any community cards or draws.
+    pub betting_round: i32,
+    /// Check, call, bet, raise or fold. An all-in is recorded as the call, bet or raise it amounts to.
+    pub action: ActionKind,
+    pub amount: Option<i32>,
+}
+
+/// The name an action is stored under.
+fn action_name(action: ActionKind) -> &'static str {
+    match action {
+        ActionKind::Check => "check",
+        ActionKind::Call => "call",
+        ActionKind::Bet => "bet",
+        ActionKind::Raise => "raise",
+        ActionKind::AllIn => "all_in",
+        ActionKind::Fold => "fold",
+        ActionKind::Draw => "draw",
+        ActionKind::StandPat => "stand_pat",
+    }
+}
+
+/// A player's betting and showdown stats in one poker variant, counted from the recorded games and actions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantStats {
+    pub variant: String,
+    pub hands: i32,
+    /// Hands in which the player called, bet or raised of their own accord.
+    pub voluntary_hands: i32,
+    /// Hands in which the player bet or raised in the first betting round.
+    pub preflop_raise_hands: i32,
+    pub bets_and_raises: i32,
+    pub calls: i32,
+    /// Hands in which the player was still in when the hand was shown down.
+    pub showdowns: i32,
+    pub showdown_wins: i32,
+    /// The most chips the player took from the pots in one game, if they have won any.
+    pub biggest_pot: Option<i32>,
+}
+
+impl VariantStats {
+    /// The share of hands in which the player voluntarily put chips in (VPIP).
+    pub fn vpip(&self) -> f64 {
+        self.voluntary_hands as f64 / self.hands.max(1) as f64
+    }
+
+    /// The share of hands in which the player bet or raised in the first betting round (PFR).
+    pub fn preflop_raise_rate(&self) -> f64 {
+        self.preflop_raise_hands as f64 / self.hands.max(1) as f64
+    }
+
+    /// Bets and raises per call, or `None` if the player has never called.
+    pub fn aggression_factor(&self) -> Option<f64> {
+        (self.calls > 0).then(|| self.bets_and_raises as f64 / self.calls as f64)
+    }
+
+    /// The share of hands the player took to a showdown.
+    pub fn went_to_showdown(&self) -> f64 {
+        self.showdowns as f64 / self.hands.max(1) as f64
+    }
+
+    /// The share of showdowns the player won, or `None` if they have not been to one.
+    pub fn won_at_showdown(&self) -> Option<f64> {
+        (self.showdowns > 0).then(|| self.showdown_wins as f64 / self.showdowns as f64)
+    }
+}
+
+impl From<VariantStats> for protocol::VariantStats {
+    fn from(stats: VariantStats) -> Self {
+        protocol::VariantStats {
+            vpip: stats.vpip(),
+            preflop_raise_rate: stats.preflop_raise_rate(),
+            aggression_factor: stats.aggression_factor(),
+            went_to_showdown: stats.went_to_showdown(),
+            won_at_showdown: stats.won_at_showdown(),
+            variant: stats.variant,
+            hands: stats.hands,
+            biggest_pot: stats.biggest_pot,
+        }
+    }
+}
+
+/// A recorded game and one player's result in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub game_number: i64,
+    pub variant: String,
+    pub lobby: String,
+    pub started_at: String,
+    /// `None` while the game is still being played.
+    pub ended_at: Option<String>,
+    pub result: GameResult,
+}
+
+/// A player's place on a leaderboard, as returned by `leaderboard`.
+#[derive(Debug)]
+pub struct LeaderboardEntry {
+    /// The player's rank, from 1, shared with any players they are tied with.
+    pub rank: i64,
+    pub stats: PlayerStats,
+    /// Chips won over the leaderboard's window, for net profit leaderboards only.
+    pub net_profit: Option<i64>,
+}
+
+impl From<LeaderboardEntry> for protocol::LeaderboardEntry {
+    fn from(entry: LeaderboardEntry) -> Self {
+        protocol::LeaderboardEntry { rank: entry.rank, player: entry.stats.into(), net_profit: entry.net_profit }
+    }
+}
+
+/// A recorded game and every player's result in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub game_number: i64,
+    pub variant: String,
+    pub lobby: String,
+    pub started_at: String,
+    /// `None` while the game is still being played.
+    pub ended_at: Option<String>,
+    /// Every chip put into the pot over the game, 0 until the game has finished.
+    pub pot: i32,
+    /// Each player's name and result, empty until the game has finished.
+    pub results: Vec<(String, GameResult)>,
 }
 
 /// Database wrapper that provides an interface for player management.
@@ -37,63 +264,160 @@ impl Database {
         }
     }
 
-    /// Registers a new player with a unique ID and an initial wallet balance of 1000.
+    /// Brings the database up to date with the current schema.
+    /// 
+    /// Creates any missing tables and adds any missing columns, keeping every existing row. Safe to run on every startup.
+    /// 
+    /// # Returns
+    /// * `Ok(())` - If the database matches the current schema.
+    /// * `Err(sqlx::Error)` - If a database error occurs, in which case nothing is changed.
+    pub async fn migrate(&self) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::raw_sql(include_str!("../schema.sql")).execute(&mut *tx).await?;
+        for (table, column, definition) in ADDED_COLUMNS {
+            let exists: bool = sqlx::query_scalar("SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2")
+                .bind(table)
+                .bind(column)
+                .fetch_one(&mut *tx)
+                .await?;
+            if !exists {
+                sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await
+    }
+
+    /// Registers a new player with a unique ID, a salted hash of their password and an initial wallet balance of 1000.
     /// 
     /// # Arguments
     /// * `name` - The player's username (must be unique).
+    /// * `password` - The player's password. Only its hash is stored.
     /// 
     /// # Returns
     /// * `Ok(String)` - The generated player ID if registration succeeds.
     /// * `Err(sqlx::Error)` - If the insertion fails (e.g., duplicate username).
-    pub async fn register_player(&self, name: &str) -> Result<String, sqlx::Error> {
+    pub async fn register_player(&self, name: &str, password: &str) -> Result<String, sqlx::Error> {
         let id = Uuid::new_v4().to_string();
-        let wallet: u32 = 1000;
-        sqlx::query("INSERT INTO players (id, name, wallet) VALUES (?1, ?2, ?3)")
+        let wallet = STARTING_WALLET;
+        sqlx::query("INSERT INTO players (id, name, wallet, password_hash) VALUES (?1, ?2, ?3, ?4)")
             .bind(&id)
             .bind(name)
-            .bind(&wallet)
+            .bind(wallet)
+            .bind(hash_password(password))
             .execute(&*self.pool)
             .await?;
         Ok(id)
     }
 
-    /// Logs in a player by checking if their username exists in the database.
+    /// Logs in a player by checking their password against the stored hash.
+    /// 
+    /// Accounts registered before passwords were added have no hash and cannot log in until they are given one with `claim_account`.
     /// 
     /// # Arguments
     /// * `name` - The player's username.
+    /// * `password` - The password the player entered.
     /// 
     /// # Returns
-    /// * `Ok(Some(String))` - The player ID if the user exists.
-    /// * `Ok(None)` - If no such user exists.
+    /// * `Ok(Some(String))` - The player ID if the user exists and the password is correct.
+    /// * `Ok(None)` - If no such user exists or the password is wrong.
     /// * `Err(sqlx::Error)` - If a database error occurs.
-    pub async fn login_player(&self, name: &str) -> Result<Option<String>, sqlx::Error> {
-        let row = sqlx::query("SELECT id FROM players WHERE name = ?1")
+    pub async fn login_player(&self, name: &str, password: &str) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query("SELECT id, password_hash FROM players WHERE name = ?1")
             .bind(name)
             .fetch_optional(&*self.pool)
             .await?;
+        Ok(row.and_then(|r| {
+            let hash: Option<String> = r.get(1);
+            hash.filter(|hash| verify_password(password, hash)).map(|_| r.get(0))
+        }))
+    }
+
+    /// Sets the password of an account registered before passwords were added.
+    /// 
+    /// Such accounts could be used by anyone who knew the username, so the first password given for one becomes its password. Accounts that already have a password are left alone.
+    /// 
+    /// # Arguments
+    /// * `name` - The player's username.
+    /// * `password` - The password to set.
+    /// 
+    /// # Returns
+    /// * `Ok(Some(String))` - The player ID if the account had no password and now has this one.
+    /// * `Ok(None)` - If no such user exists or the account already has a password.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn claim_account(&self, name: &str, password: &str) -> Result<Option<String>, sqlx::Error> {
+        // a single statement, so two connections cannot both set the password
+        let row = sqlx::query("UPDATE players SET password_hash = ?2 WHERE name = ?1 AND password_hash IS NULL RETURNING id")
+            .bind(name)
+            .bind(hash_password(password))
+            .fetch_optional(&*self.pool)
+            .await?;
         Ok(row.map(|r| r.get(0)))
     }
 
-    /// Retrieves a player's statistics (games played, games won, and wallet balance) by username.
+    /// Starts a session for a logged in player.
     /// 
     /// # Arguments
-    /// * `username` - The player's username.
+    /// * `player_id` - The ID of the player.
     /// 
     /// # Returns
-    /// * `Ok(PlayerStats)` - The player's statistics if found.
-    /// * `Err(sqlx::Error)` - If the user does not exist or a database error occurs.
-    pub async fn player_stats(&self, username: &str) -> Result<PlayerStats, sqlx::Error> {
-        let row = sqlx::query("SELECT games_played, games_won, wallet FROM players WHERE name = ?1")
-            .bind(username)
-            .fetch_one(&*self.pool)
+    /// * `Ok(String)` - The session token, which resumes the session on a new connection for `SESSION_DURATION_SECS`.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn create_session(&self, player_id: &str) -> Result<String, sqlx::Error> {
+        let token = to_hex(&rand::rng().random::<[u8; 32]>());
+        sqlx::query("INSERT INTO sessions (token_hash, player_id, expires_at) VALUES (?1, ?2, strftime('%s', 'now') + ?3)")
+            .bind(hash_token(&token))
+            .bind(player_id)
+            .bind(SESSION_DURATION_SECS)
+            .execute(&*self.pool)
             .await?;
+        Ok(token)
+    }
+
+    /// Resumes a session from its token.
+    /// 
+    /// # Arguments
+    /// * `token` - The token given when the session was started.
+    /// 
+    /// # Returns
+    /// * `Ok(Some((String, String)))` - The player's ID and username if the token is valid and has not expired.
+    /// * `Ok(None)` - If the token is unknown or has expired.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn resume_session(&self, token: &str) -> Result<Option<(String, String)>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT players.id, players.name FROM sessions JOIN players ON players.id = sessions.player_id
+             WHERE sessions.token_hash = ?1 AND sessions.expires_at > strftime('%s', 'now')",
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&*self.pool)
+        .await?;
+        Ok(row.map(|r| (r.get(0), r.get(1))))
+    }
+
+    /// Retrieves a player's statistics by ID or username, whether or not they are online.
+    /// 
+    /// # Arguments
+    /// * `player` - The player's ID or username. An ID is matched first.
+    /// 
+    /// # Returns
+    /// * `Ok(PlayerStats)` - The player's statistics, with their stored ID, if found.
+    /// * `Err(sqlx::Error)` - If the player does not exist or a database error occurs.
+    pub async fn player_stats(&self, player: &str) -> Result<PlayerStats, sqlx::Error> {
+        let row = sqlx::query(&format!(
+            "SELECT id, name, games_played, games_won, wallet, {NET_CHIPS} FROM players WHERE id = ?1 OR name = ?1 ORDER BY id = ?1 DESC LIMIT 1",
+        ))
+        .bind(player)
+        .fetch_one(&*self.pool)
+        .await?;
 
         Ok(PlayerStats {
-            games_played: row.get(0),
-            games_won: row.get(1),
-            wallet: row.get(2),
-            id: Uuid::new_v4().to_string(),
-            name: username.to_string(),
+            id: row.get(0),
+            name: row.get(1),
+            games_played: row.get(2),
+            games_won: row.get(3),
+            wallet: row.get(4),
+            net_chips: row.get(5),
         })
     }
 
@@ -113,10 +437,10 @@ impl Database {
         Ok(row.get(0))
     }
 
-    /// Updates a player's statistics (games played, games won, wallet balance) in the database.
+    /// Adds the games a player has played and won since their stats were last saved, and saves their wallet balance.
     /// 
     /// # Arguments
-    /// * `player` - A reference to the `Player` struct containing updated stats.
+    /// * `player` - The player, whose `games_played` and `games_won` count only the games that have not been saved yet.
     /// 
     /// # Returns
     /// * `Ok(())` - If the update is successful.
@@ -133,8 +457,353 @@ impl Database {
         .await?;
         Ok(())
     }
+
+    /// Records the start of a game and gives it the next game number.
+    /// 
+    /// # Arguments
+    /// * `variant` - The name of the poker variant being played.
+    /// * `lobby` - The name of the lobby the game is played in.
+    /// 
+    /// # Returns
+    /// * `Ok(i64)` - The game number, counting up from 0.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn start_game(&self, variant: &str, lobby: &str) -> Result<i64, sqlx::Error> {
+        // a single statement, so two lobbies starting at once cannot get the same number
+        let row = sqlx::query(
+            "INSERT INTO games (game_number, variant, lobby, started_at)
+             VALUES ((SELECT COALESCE(MAX(game_number) + 1, 0) FROM games), ?1, ?2, datetime('now'))
+             RETURNING game_number",
+        )
+        .bind(variant)
+        .bind(lobby)
+        .fetch_one(&*self.pool)
+        .await?;
+        Ok(row.get(0))
+    }
+
+    /// Records the end of a game, every player's result in it and every betting action, in one transaction.
+    /// 
+    /// # Arguments
+    /// * `game_number` - The number given by `start_game`.
+    /// * `pot` - Every chip put into the pot over the game.
+    /// * `results` - The result of each player who took part.
+    /// * `actions` - Every move made in the game's betting rounds, in order.
+    /// 
+    /// # Returns
+    /// * `Ok(())` - If the game was recorded.
+    /// * `Err(sqlx::Error)` - If a database error occurs, in which case nothing is recorded.
+    pub async fn finish_game(&self, game_number: i64, pot: i32, results: &[GameResult], actions: &[RecordedAction]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("UPDATE games SET ended_at = datetime('now'), pot = ?2 WHERE game_number = ?1")
+            .bind(game_number)
+            .bind(pot)
+            .execute(&mut *tx)
+            .await?;
+        for result in results {
+            sqlx::query(
+                "INSERT INTO game_participants (game_number, player_id, net_result, final_hand, won, showdown, collected)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )
+            .bind(game_number)
+            .bind(&result.player_id)
+            .bind(result.net_result)
+            .bind(&result.final_hand)
+            .bind(result.won)
+            .bind(result.showdown)
+            .bind(result.collected)
+            .execute(&mut *tx)
+            .await?;
+        }
+        for (seq, action) in actions.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO game_actions (game_number, seq, player_id, betting_round, action, amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .bind(game_number)
+            .bind(seq as i64)
+            .bind(&action.player_id)
+            .bind(action.betting_round)
+            .bind(action_name(action.action))
+            .bind(action.amount)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    /// Retrieves a player's result in a game.
+    /// 
+    /// # Arguments
+    /// * `game_number` - The game's number.
+    /// * `username` - The player's username.
+    /// 
+    /// # Returns
+    /// * `Ok(Some(GameRecord))` - The game and the player's result if they took part in it.
+    /// * `Ok(None)` - If there is no such game or the player was not in it.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn game_result(&self, game_number: i64, username: &str) -> Result<Option<GameRecord>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT games.variant, games.lobby, games.started_at, games.ended_at,
+                    players.id, game_participants.net_result, game_participants.final_hand, game_participants.won,
+                    game_participants.showdown, game_participants.collected
+             FROM game_participants
+             JOIN games ON games.game_number = game_participants.game_number
+             JOIN players ON players.id = game_participants.player_id
+             WHERE game_participants.game_number = ?1 AND players.name = ?2",
+        )
+        .bind(game_number)
+        .bind(username)
+        .fetch_optional(&*self.pool)
+        .await?;
+        Ok(row.map(|r| GameRecord {
+            game_number,
+            variant: r.get(0),
+            lobby: r.get(1),
+            started_at: r.get(2),
+            ended_at: r.get(3),
+            result: GameResult {
+                player_id: r.get(4),
+                net_result: r.get(5),
+                final_hand: r.get(6),
+                won: r.get(7),
+                showdown: r.get(8),
+                collected: r.get(9),
+            },
+        }))
+    }
+
+    /// Retrieves a game and the result of every player who took part.
+    /// 
+    /// # Arguments
+    /// * `game_number` - The game's number.
+    /// 
+    /// # Returns
+    /// * `Ok(Some(Game))` - The game, if it has been recorded.
+    /// * `Ok(None)` - If there is no such game.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn game(&self, game_number: i64) -> Result<Option<Game>, sqlx::Error> {
+        let Some(row) = sqlx::query("SELECT variant, lobby, started_at, ended_at, pot FROM games WHERE game_number = ?1")
+            .bind(game_number)
+            .fetch_optional(&*self.pool)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let results = sqlx::query(
+            "SELECT players.name, players.id, game_participants.net_result, game_participants.final_hand, game_participants.won,
+                    game_participants.showdown, game_participants.collected
+             FROM game_participants
+             JOIN players ON players.id = game_participants.player_id
+             WHERE game_participants.game_number = ?1
+             ORDER BY game_participants.net_result DESC, players.name",
+        )
+        .bind(game_number)
+        .fetch_all(&*self.pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            let result = GameResult {
+                player_id: r.get(1),
+                net_result: r.get(2),
+                final_hand: r.get(3),
+                won: r.get(4),
+                showdown: r.get(5),
+                collected: r.get(6),
+            };
+            (r.get(0), result)
+        })
+        .collect();
+        Ok(Some(Game {
+            game_number,
+            variant: row.get(0),
+            lobby: row.get(1),
+            started_at: row.get(2),
+            ended_at: row.get(3),
+            pot: row.get(4),
+            results,
+        }))
+    }
+
+    /// Retrieves a page of a leaderboard.
+    /// 
+    /// Tied players share a rank and are listed by name, so every page is the same however often it is asked for. The win rate leaderboard only counts players with at least its minimum number of games, and the net profit leaderboard only players who finished a game in its window.
+    /// 
+    /// # Arguments
+    /// * `metric` - What to rank the players by.
+    /// * `page` - The page to retrieve, from 1.
+    /// * `per_page` - The number of players on a page.
+    /// 
+    /// # Returns
+    /// * `Ok(Vec<LeaderboardEntry>)` - The players on the page, best first.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn leaderboard(&self, metric: LeaderboardMetric, page: u32, per_page: u32) -> Result<Vec<LeaderboardEntry>, sqlx::Error> {
+        // (score, FROM and WHERE clauses), with ?1 the metric's parameter
+        let (score, from) = match metric {
+            LeaderboardMetric::Wallet => ("players.wallet", "players WHERE ?1 IS NULL"),
+            LeaderboardMetric::Wins => ("players.games_won", "players WHERE ?1 IS NULL"),
+            LeaderboardMetric::WinRate { .. } => (
+                "CAST(players.games_won AS REAL) / players.games_played",
+                "players WHERE players.games_played >= MAX(?1, 1)",
+            ),
+            LeaderboardMetric::NetProfit { .. } => (
+                "SUM(game_participants.net_result)",
+                "game_participants
+                 JOIN games ON games.game_number = game_participants.game_number
+                 JOIN players ON players.id = game_participants.player_id
+                 WHERE games.ended_at IS NOT NULL AND (?1 IS NULL OR games.ended_at >= datetime('now', '-' || ?1 || ' days'))
+                 GROUP BY players.id",
+            ),
+        };
+        let parameter = match metric {
+            LeaderboardMetric::WinRate { min_games } => Some(min_games),
+            LeaderboardMetric::NetProfit { days } => days,
+            _ => None,
+        };
+        let query = format!(
+            "SELECT players.id, players.name, players.games_played, players.games_won, players.wallet,
+                    {score} AS score, RANK() OVER (ORDER BY {score} DESC) AS rank, {NET_CHIPS}
+             FROM {from}
+             ORDER BY score DESC, players.name
+             LIMIT ?2 OFFSET ?3",
+        );
+        let rows = sqlx::query(&query)
+            .bind(parameter)
+            .bind(per_page)
+            .bind(page.saturating_sub(1) as i64 * per_page as i64)
+            .fetch_all(&*self.pool)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| LeaderboardEntry {
+                rank: r.get(6),
+                stats: PlayerStats {
+                    id: r.get(0),
+                    name: r.get(1),
+                    games_played: r.get(2),
+                    games_won: r.get(3),
+                    wallet: r.get(4),
+                    net_chips: r.get(7),
+                },
+                net_profit: matches!(metric, LeaderboardMetric::NetProfit { .. }).then(|| r.get(5)),
+            })
+            .collect())
+    }
+
+    /// Retrieves a player's betting and showdown stats in each variant they have played.
+    /// 
+    /// # Arguments
+    /// * `player_id` - The player's stored ID.
+    /// 
+    /// # Returns
+    /// * `Ok(Vec<VariantStats>)` - The player's stats per variant, by variant name, empty if they have no recorded games.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn variant_stats(&self, player_id: &str) -> Result<Vec<VariantStats>, sqlx::Error> {
+        // counts the player's recorded actions in the game of the current row
+        let actions = |filter: &str| {
+            format!(
+                "(SELECT COUNT(*) FROM game_actions WHERE game_actions.game_number = game_participants.game_number
+                  AND game_actions.player_id = game_participants.player_id AND {})",
+                filter
+            )
+        };
+        let query = format!(
+            "SELECT games.variant,
+                    COUNT(*),
+                    SUM({voluntary} > 0),
+                    SUM({preflop_raises} > 0),
+                    SUM({bets_and_raises}),
+                    SUM({calls}),
+                    SUM(game_participants.showdown),
+                    SUM(game_participants.showdown AND game_participants.won),
+                    MAX(NULLIF(game_participants.collected, 0))
+             FROM game_participants
+             JOIN games ON games.game_number = game_participants.game_number
+             WHERE game_participants.player_id = ?1
+             GROUP BY games.variant
+             ORDER BY games.variant",
+            voluntary = actions("game_actions.action IN ('call', 'bet', 'raise')"),
+            preflop_raises = actions("game_actions.betting_round = 0 AND game_actions.action IN ('bet', 'raise')"),
+            bets_and_raises = actions("game_actions.action IN ('bet', 'raise')"),
+            calls = actions("game_actions.action = 'call'"),
+        );
+        let rows = sqlx::query(&query).bind(player_id).fetch_all(&*self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|r| VariantStats {
+                variant: r.get(0),
+                hands: r.get(1),
+                voluntary_hands: r.get(2),
+                preflop_raise_hands: r.get(3),
+                bets_and_raises: r.get(4),
+                calls: r.get(5),
+                showdowns: r.get(6),
+                showdown_wins: r.get(7),
+                biggest_pot: r.get(8),
+            })
+            .collect())
+    }
+
+    /// Checks whether a player is an administrator.
+    /// 
+    /// # Arguments
+    /// * `username` - The player's username.
+    /// 
+    /// # Returns
+    /// * `Ok(bool)` - Whether the player is an administrator, `false` if there is no such player.
+    /// * `Err(sqlx::Error)` - If a database error occurs.
+    pub async fn is_admin(&self, username: &str) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query("SELECT is_admin FROM players WHERE name = ?1")
+            .bind(username)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.is_some_and(|r| r.get::<bool, _>(0)))
+    }
+
+    /// Makes the named players administrators, in one transaction. Players who already are stay so.
+    /// 
+    /// # Arguments
+    /// * `usernames` - The usernames of the players to make administrators.
+    /// 
+    /// # Returns
+    /// * `Ok(Vec<String>)` - The usernames that did not match any player, in the order given.
+    /// * `Err(sqlx::Error)` - If a database error occurs, in which case nobody is made an administrator.
+    pub async fn grant_admins(&self, usernames: &[&str]) -> Result<Vec<String>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut unknown = Vec::new();
+        for &username in usernames {
+            let updated = sqlx::query("UPDATE players SET is_admin = 1 WHERE name = ?1")
+                .bind(username)
+                .execute(&mut *tx)
+                .await?;
+            if updated.rows_affected() == 0 {
+                unknown.push(username.to_string());
+            }
+        }
+        tx.commit().await?;
+        Ok(unknown)
+    }
+
+    /// Resets the game numbering to 0 and clears every player's retained information, in one transaction.
+    /// 
+    /// The game history is deleted, so the next game is numbered 0, and every player's games played and won go back to 0 and their wallet to `STARTING_WALLET`. Accounts and sessions are kept.
+    /// 
+    /// # Returns
+    /// * `Ok(())` - If the reset succeeded.
+    /// * `Err(sqlx::Error)` - If a database error occurs, in which case nothing is changed.
+    pub async fn reset_history(&self) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM game_actions").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM game_participants").execute(&mut *tx).await?;
+        // game numbers are counted from the highest recorded game, so an empty table starts again at 0
+        sqlx::query("DELETE FROM games").execute(&mut *tx).await?;
+        sqlx::query("UPDATE players SET games_played = 0, games_won = 0, wallet = ?1")
+            .bind(STARTING_WALLET)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
 }
 
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,19 +811,7 @@ mod tests {
     /// Sets up an in-memory SQLite database for testing.
     async fn setup_database() -> Database {
         let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
-        sqlx::query(
-            "CREATE TABLE players (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL UNIQUE,
-                games_played INTEGER DEFAULT 0,
-                games_won INTEGER DEFAULT 0,
-                wallet INTEGER DEFAULT 1000
-            )"
-        )
-        .execute(&pool)
-        .await
-        .unwrap();
-
+        sqlx::raw_sql(include_str!("../schema.sql")).execute(&pool).await.unwrap();
         Database::new(pool)
     }
 
@@ -163,7 +820,7 @@ mod tests {
     async fn test_statistics_across_multiple_instantiations() {
         let db = setup_database().await;
         let player_name = "test_player";
-        db.register_player(player_name).await.unwrap();
+        db.register_player(player_name, "secret").await.unwrap();
         
         for _ in 0..100 {
             sqlx::query("UPDATE players SET games_played = games_played + 1, games_won = games_won + 1 WHERE name = ?1")
@@ -187,7 +844,7 @@ mod tests {
 
         // Register a player
         let player_name = "test_player";
-        db.register_player(player_name).await.unwrap();
+        db.register_player(player_name, "secret").await.unwrap();
 
         // Check player stats
         let stats = db.player_stats(player_name).await.unwrap();
@@ -203,14 +860,340 @@ mod tests {
 
         // Register a player
         let player_name = "unique_player";
-        db.register_player(player_name).await.unwrap();
+        db.register_player(player_name, "secret").await.unwrap();
 
         // Attempt to register another player with the same name
-        let result = db.register_player(player_name).await;
+        let result = db.register_player(player_name, "secret").await;
 
         // Check that the second registration attempt fails
         assert!(result.is_err());
     }
-}
 
+    /// Tests that logging in needs the right password
+    #[tokio::test]
+    async fn test_login_checks_password() {
+        let db = setup_database().await;
+        let id = db.register_player("alice", "hunter2").await.unwrap();
+
+        assert_eq!(db.login_player("alice", "hunter2").await.unwrap(), Some(id));
+        assert_eq!(db.login_player("alice", "hunter3").await.unwrap(), None);
+        assert_eq!(db.login_player("bob", "hunter2").await.unwrap(), None);
+
+        // the password itself is never stored
+        let hash: String = sqlx::query("SELECT password_hash FROM players WHERE name = 'alice'")
+            .fetch_one(&*db.pool)
+            .await
+            .unwrap()
+            .get(0);
+        assert!(!hash.contains("hunter2"));
+    }
+
+    /// Tests that an account made before passwords were added can be given one, but only once
+    #[tokio::test]
+    async fn test_claim_account() {
+        let db = setup_database().await;
+        sqlx::query("INSERT INTO players (id, name, wallet) VALUES ('old', 'alice', 1000)")
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.login_player("alice", "hunter2").await.unwrap(), None);
+
+        assert_eq!(db.claim_account("alice", "hunter2").await.unwrap(), Some("old".to_string()));
+        assert_eq!(db.login_player("alice", "hunter2").await.unwrap(), Some("old".to_string()));
+        assert_eq!(db.claim_account("alice", "hunter3").await.unwrap(), None);
+        assert_eq!(db.login_player("alice", "hunter3").await.unwrap(), None);
+        assert_eq!(db.claim_account("bob", "hunter2").await.unwrap(), None);
+    }
+
+    /// Tests that a database made before the newer tables and columns is brought up to date without losing rows
+    #[tokio::test]
+    async fn test_migrate_existing_database() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql(
+            "CREATE TABLE players (id TEXT PRIMARY KEY, name TEXT UNIQUE NOT NULL, games_played INTEGER DEFAULT 0, games_won INTEGER DEFAULT 0, wallet INTEGER DEFAULT 0);
+            INSERT INTO players (id, name, games_played, games_won, wallet) VALUES ('old', 'alice', 3, 1, 1200);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let db = Database::new(pool);
+        db.migrate().await.unwrap();
+        // running it again changes nothing
+        db.migrate().await.unwrap();
+
+        let stats = db.player_stats("alice").await.unwrap();
+        assert_eq!((stats.games_played, stats.games_won, stats.wallet), (3, 1, 1200));
+        assert!(!db.is_admin("alice").await.unwrap());
+        assert_eq!(db.claim_account("alice", "hunter2").await.unwrap(), Some("old".to_string()));
+        let game_number = db.start_game("Texas Hold'em", "table1").await.unwrap();
+        let results = [GameResult { player_id: "old".to_string(), net_result: 0, final_hand: None, won: true, showdown: false, collected: 30 }];
+        db.finish_game(game_number, 30, &results, &[]).await.unwrap();
+        assert_eq!(db.game(game_number).await.unwrap().unwrap().pot, 30);
+    }
+
+    /// Tests that a session token resumes the session until it expires
+    #[tokio::test]
+    async fn test_resume_session() {
+        let db = setup_database().await;
+        let id = db.register_player("alice", "hunter2").await.unwrap();
+        let token = db.create_session(&id).await.unwrap();
+
+        assert_eq!(db.resume_session(&token).await.unwrap(), Some((id, "alice".to_string())));
+        assert_eq!(db.resume_session("not-a-token").await.unwrap(), None);
+
+        sqlx::query("UPDATE sessions SET expires_at = strftime('%s', 'now') - 1")
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+        assert_eq!(db.resume_session(&token).await.unwrap(), None);
+    }
+
+    /// Tests that games are numbered from 0 and a player's result can be looked up by game number
+    //S-FR-8, S-PR-6
+    #[tokio::test]
+    async fn test_game_history() {
+        let db = setup_database().await;
+        let alice = db.register_player("alice", "pw").await.unwrap();
+        let bob = db.register_player("bob", "pw").await.unwrap();
+
+        assert_eq!(db.start_game("Texas Hold'em", "table1").await.unwrap(), 0);
+        assert_eq!(db.start_game("Five Card Draw", "table2").await.unwrap(), 1);
+
+        let results = [
+            GameResult { player_id: alice.clone(), net_result: 40, final_hand: Some("Ah Kh".to_string()), won: true, showdown: false, collected: 80 },
+            GameResult { player_id: bob.clone(), net_result: -40, final_hand: None, won: false, showdown: false, collected: 0 },
+        ];
+        db.finish_game(1, 80, &results, &[]).await.unwrap();
 
+        let record = db.game_result(1, "alice").await.unwrap().unwrap();
+        assert_eq!(record.lobby, "table2");
+        assert!(record.ended_at.is_some());
+        assert_eq!(record.result, results[0]);
+        assert_eq!(db.game_result(1, "bob").await.unwrap().unwrap().result, results[1]);
+        // game 0 has not finished, and nobody played game 2
+        assert_eq!(db.game_result(0, "alice").await.unwrap(), None);
+        assert_eq!(db.game_result(2, "alice").await.unwrap(), None);
+    }
+
+    /// Tests that a reset restarts the game numbering at 0 and clears every player's stats and history
+    //S-FR-9, S-FR-10
+    #[tokio::test]
+    async fn test_reset_history() {
+        let db = setup_database().await;
+        let alice = db.register_player("alice", "pw").await.unwrap();
+        assert_eq!(db.start_game("Texas Hold'em", "table1").await.unwrap(), 0);
+        let result = GameResult { player_id: alice, net_result: 250, final_hand: None, won: true, showdown: false, collected: 500 };
+        db.finish_game(0, 500, &[result], &[]).await.unwrap();
+        sqlx::query("UPDATE players SET games_played = 3, games_won = 1, wallet = 1250")
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+
+        db.reset_history().await.unwrap();
+
+        let stats = db.player_stats("alice").await.unwrap();
+        assert_eq!((stats.games_played, stats.games_won, stats.wallet), (0, 0, STARTING_WALLET));
+        assert_eq!(db.game_result(0, "alice").await.unwrap(), None);
+        assert_eq!(db.start_game("Texas Hold'em", "table1").await.unwrap(), 0);
+        // the account itself is kept
+        assert!(db.login_player("alice", "pw").await.unwrap().is_some());
+    }
+
+    /// Tests that only players flagged in the database are administrators
+    #[tokio::test]
+    async fn test_is_admin() {
+        let db = setup_database().await;
+        db.register_player("alice", "pw").await.unwrap();
+        db.register_player("root", "pw").await.unwrap();
+        sqlx::query("UPDATE players SET is_admin = 1 WHERE name = 'root'").execute(&*db.pool).await.unwrap();
+        assert!(db.is_admin("root").await.unwrap());
+        assert!(!db.is_admin("alice").await.unwrap());
+        assert!(!db.is_admin("nobody").await.unwrap());
+    }
+
+    /// Tests that named players are made administrators and unknown names are reported
+    #[tokio::test]
+    async fn test_grant_admins() {
+        let db = setup_database().await;
+        db.register_player("alice", "pw").await.unwrap();
+        db.register_player("bob", "pw").await.unwrap();
+        db.register_player("root", "pw").await.unwrap();
+
+        assert_eq!(db.grant_admins(&["root", "nobody", "alice"]).await.unwrap(), vec!["nobody".to_string()]);
+        assert!(db.is_admin("root").await.unwrap());
+        assert!(db.is_admin("alice").await.unwrap());
+        assert!(!db.is_admin("bob").await.unwrap());
+        // granting again changes nothing
+        assert!(db.grant_admins(&["root"]).await.unwrap().is_empty());
+        assert!(db.is_admin("root").await.unwrap());
+    }
+
+    /// Tests that stats can be looked up by the stored ID or by name, with the derived numbers filled in
+    //S-PR-5, S-PR-7
+    #[tokio::test]
+    async fn test_player_stats_by_id_or_name() {
+        let db = setup_database().await;
+        let id = db.register_player("alice", "pw").await.unwrap();
+        sqlx::query("UPDATE players SET games_played = 4, games_won = 1, wallet = 850 WHERE id = ?1")
+            .bind(&id)
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+        // the net is counted from the recorded games, whatever the wallet was set to
+        for net_result in [-200, 50] {
+            let game_number = db.start_game("Texas Hold'em", "table1").await.unwrap();
+            let results = [GameResult {
+                player_id: id.clone(),
+                net_result,
+                final_hand: None,
+                won: net_result > 0,
+                showdown: false,
+                collected: if net_result > 0 { 200 } else { 0 },
+            }];
+            db.finish_game(game_number, 200, &results, &[]).await.unwrap();
+        }
+
+        let by_id = db.player_stats(&id).await.unwrap();
+        let by_name = db.player_stats("alice").await.unwrap();
+        assert_eq!((by_id.id.as_str(), by_id.name.as_str()), (id.as_str(), "alice"));
+        assert_eq!(by_name.id, id);
+        assert_eq!(by_id.win_rate(), 0.25);
+        assert_eq!(by_id.net_chips, -150);
+        assert!(db.player_stats("nobody").await.is_err());
+
+        let new = db.register_player("bob", "pw").await.unwrap();
+        assert_eq!(db.player_stats(&new).await.unwrap().win_rate(), 0.0);
+    }
+
+    /// Tests each leaderboard metric, the minimum games for win rate, the ranking of ties and paging
+    #[tokio::test]
+    async fn test_leaderboards() {
+        let db = setup_database().await;
+        // name, games played, games won, wallet
+        for (name, played, won, wallet) in [("alice", 10, 6, 1500), ("bob", 4, 4, 1200), ("carol", 10, 3, 1500), ("dave", 8, 6, 400)] {
+            let id = db.register_player(name, "pw").await.unwrap();
+            sqlx::query("UPDATE players SET games_played = ?1, games_won = ?2, wallet = ?3 WHERE id = ?4")
+                .bind(played)
+                .bind(won)
+                .bind(wallet)
+                .bind(&id)
+                .execute(&*db.pool)
+                .await
+                .unwrap();
+        }
+        let ranking = |entries: Vec<LeaderboardEntry>| {
+            entries.into_iter().map(|e| (e.rank, e.stats.name)).collect::<Vec<_>>()
+        };
+        let ranked = |names: &[(i64, &str)]| names.iter().map(|&(rank, name)| (rank, name.to_string())).collect::<Vec<_>>();
+
+        let wallet = db.leaderboard(LeaderboardMetric::Wallet, 1, 10).await.unwrap();
+        assert_eq!(ranking(wallet), ranked(&[(1, "alice"), (1, "carol"), (3, "bob"), (4, "dave")]));
+        let wins = db.leaderboard(LeaderboardMetric::Wins, 1, 10).await.unwrap();
+        assert_eq!(ranking(wins), ranked(&[(1, "alice"), (1, "dave"), (3, "bob"), (4, "carol")]));
+        // bob has won every game but has not played enough of them
+        let win_rate = db.leaderboard(LeaderboardMetric::WinRate { min_games: 5 }, 1, 10).await.unwrap();
+        assert_eq!(ranking(win_rate), ranked(&[(1, "dave"), (2, "alice"), (3, "carol")]));
+        let second_page = db.leaderboard(LeaderboardMetric::Wallet, 2, 3).await.unwrap();
+        assert_eq!(ranking(second_page), ranked(&[(4, "dave")]));
+
+        let alice = db.player_stats("alice").await.unwrap().id;
+        let bob = db.player_stats("bob").await.unwrap().id;
+        for (net, won) in [(50, true), (-20, false)] {
+            let game_number = db.start_game("Five Card Draw", "table1").await.unwrap();
+            let results = [
+                GameResult { player_id: alice.clone(), net_result: net, final_hand: None, won, showdown: false, collected: if won { 100 } else { 0 } },
+                GameResult { player_id: bob.clone(), net_result: -net, final_hand: None, won: !won, showdown: false, collected: if won { 0 } else { 100 } },
+            ];
+            db.finish_game(game_number, 100, &results, &[]).await.unwrap();
+        }
+        // a game from long ago only counts towards the all-time leaderboard
+        let old_game = db.start_game("Five Card Draw", "table1").await.unwrap();
+        let results = [GameResult { player_id: bob.clone(), net_result: 500, final_hand: None, won: true, showdown: false, collected: 1000 }];
+        db.finish_game(old_game, 1000, &results, &[]).await.unwrap();
+        sqlx::query("UPDATE games SET ended_at = datetime('now', '-30 days') WHERE game_number = ?1")
+            .bind(old_game)
+            .execute(&*db.pool)
+            .await
+            .unwrap();
+
+        let week = db.leaderboard(LeaderboardMetric::NetProfit { days: Some(7) }, 1, 10).await.unwrap();
+        assert_eq!(week.iter().map(|e| (e.stats.name.as_str(), e.net_profit)).collect::<Vec<_>>(), [("alice", Some(30)), ("bob", Some(-30))]);
+        let all_time = db.leaderboard(LeaderboardMetric::NetProfit { days: None }, 1, 10).await.unwrap();
+        assert_eq!(all_time.iter().map(|e| (e.stats.name.as_str(), e.net_profit)).collect::<Vec<_>>(), [("bob", Some(470)), ("alice", Some(30))]);
+    }
+
+    /// Tests that VPIP, preflop raises, aggression, showdowns and the biggest pot are counted from the recorded actions, per variant
+    #[tokio::test]
+    async fn test_variant_stats() {
+        let db = setup_database().await;
+        let alice = db.register_player("alice", "pw").await.unwrap();
+        let bob = db.register_player("bob", "pw").await.unwrap();
+        let act = |player_id: &String, betting_round, action, amount| RecordedAction {
+            player_id: player_id.clone(),
+            betting_round,
+            action,
+            amount,
+        };
+        let result = |player_id: &String, net_result, won, showdown, collected| GameResult {
+            player_id: player_id.clone(),
+            net_result,
+            final_hand: None,
+            won,
+            showdown,
+            collected,
+        };
+
+        // alice raises before the flop and takes the pot with a bet on the flop
+        let game = db.start_game("Texas Hold'em", "table1").await.unwrap();
+        let actions = [
+            act(&alice, 0, ActionKind::Raise, Some(20)),
+            act(&bob, 0, ActionKind::Call, Some(10)),
+            act(&alice, 1, ActionKind::Bet, Some(20)),
+            act(&bob, 1, ActionKind::Fold, None),
+        ];
+        let results = [result(&alice, 20, true, false, 60), result(&bob, -20, false, false, 0)];
+        db.finish_game(game, 60, &results, &actions).await.unwrap();
+
+        // alice checks her blind, calls on the flop and loses at the showdown
+        let game = db.start_game("Texas Hold'em", "table1").await.unwrap();
+        let actions = [
+            act(&bob, 0, ActionKind::Call, Some(10)),
+            act(&alice, 0, ActionKind::Check, None),
+            act(&bob, 1, ActionKind::Bet, Some(80)),
+            act(&alice, 1, ActionKind::Call, Some(80)),
+        ];
+        let results = [result(&alice, -100, false, true, 0), result(&bob, 100, true, true, 200)];
+        db.finish_game(game, 200, &results, &actions).await.unwrap();
+
+        // alice folds straight away
+        let game = db.start_game("Five Card Draw", "table2").await.unwrap();
+        let results = [result(&alice, -5, false, false, 0), result(&bob, 5, true, false, 10)];
+        db.finish_game(game, 10, &results, &[act(&alice, 0, ActionKind::Fold, None)]).await.unwrap();
+
+        let stats = db.variant_stats(&alice).await.unwrap();
+        assert_eq!(stats.len(), 2);
+        let (draw, holdem) = (&stats[0], &stats[1]);
+        assert_eq!((draw.variant.as_str(), draw.hands, draw.voluntary_hands), ("Five Card Draw", 1, 0));
+        assert_eq!((draw.aggression_factor(), draw.won_at_showdown(), draw.biggest_pot), (None, None, None));
+
+        assert_eq!(holdem.hands, 2);
+        assert_eq!(holdem.vpip(), 1.0);
+        assert_eq!(holdem.preflop_raise_rate(), 0.5);
+        assert_eq!(holdem.aggression_factor(), Some(2.0));
+        assert_eq!(holdem.went_to_showdown(), 0.5);
+        assert_eq!(holdem.won_at_showdown(), Some(0.0));
+        assert_eq!(holdem.biggest_pot, Some(60));
+        assert_eq!(db.variant_stats(&bob).await.unwrap()[1].biggest_pot, Some(200));
+
+        // carol is all-in for 50 and wins the main pot of 150, alice wins the side pot of 400 that carol could not contest
+        let carol = db.register_player("carol", "pw").await.unwrap();
+        let game = db.start_game("Texas Hold'em", "table1").await.unwrap();
+        let results = [result(&alice, 150, true, true, 400), result(&bob, -250, false, true, 0), result(&carol, 100, true, true, 150)];
+        db.finish_game(game, 550, &results, &[]).await.unwrap();
+        assert_eq!(db.variant_stats(&carol).await.unwrap()[0].biggest_pot, Some(150));
+        assert_eq!(db.variant_stats(&alice).await.unwrap()[1].biggest_pot, Some(400));
+
+        db.reset_history().await.unwrap();
+        assert!(db.variant_stats(&alice).await.unwrap().is_empty());
+    }
+}
@@ -1,47 +1,161 @@
 //! A module for creating and managing a deck of cards
 //! 
-//! This module contains the `Deck` struct, which represents a deck of 52 playing cards. The deck can be shuffled, and cards can be dealt from the top of the deck.
-use rand::seq::SliceRandom;
-use rand::rng;
+//! This module contains the `Deck` struct, which represents a deck of 52 playing cards, plus up to two jokers for variants that need them. The deck can be shuffled, and cards can be dealt from the top of the deck.
+//! 
+//! Cards exchanged in a drawing round are kept on a discard pile. When the deck runs out, the discards are shuffled into a new stub, the way a dealer does in draw poker. Dealing only fails once both the deck and the discards are empty.
+//! 
+//! # Verifiable shuffles
+//! 
+//! Every shuffle is driven by a 32-byte seed, so a hand can be replayed exactly. Before the deal the server publishes the SHA-256 hash of the seed as a commitment, and after the hand it reveals the seed. Anyone can then check the seed against the commitment and re-derive the deck order:
+//! 
+//! 1. Start from a new deck in its sorted order (see `Deck::new` and `Deck::with_jokers`).
+//! 2. Seed a ChaCha20 random number generator with the 32-byte seed.
+//! 3. Run a Fisher-Yates shuffle: for `i` from the last index down to 1, draw `j` uniformly from `0..=i` and swap cards `i` and `j`.
+//! 4. To draw `j`, take the next `u32` from the generator and reject it if it is at or above the largest multiple of `i + 1` that fits in 2^32; otherwise `j` is the value modulo `i + 1`.
+//! 
+//! Discards reshuffled into the stub use the same generator, continuing from where the deal left off.
+use crate::card::{Card, Rank, Suit};
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
 
 #[derive (Debug, Clone)]
 pub struct Deck {
     next_card_index: i32,
-    cards: Vec<i32>,
+    cards: Vec<Card>,
+    discards: Vec<Card>,
+    jokers: usize,
+    seed: [u8; 32],
+    rng: ChaCha20Rng,
 }
 
 impl Deck {
-    /// Create a new 52-card deck
+    /// Create a new 52-card deck, sorted by suit and then from Ace to King
     pub fn new() -> Deck{
-        Deck{next_card_index: 0, cards: (0..52).collect()}        
+        let cards = Suit::ALL
+            .iter()
+            .flat_map(|&suit| Rank::ALL.iter().map(move |&rank| Card::new(rank, suit)))
+            .collect();
+        Deck{next_card_index: 0, cards, discards: Vec::new(), jokers: 0, seed: [0; 32], rng: ChaCha20Rng::from_seed([0; 32])}
     }
 
-    /// Shuffle the deck
+    /// Create a new 52-card deck with up to two jokers added at the bottom
+    pub fn with_jokers(jokers: usize) -> Deck{
+        assert!(jokers <= 2);
+        let mut deck = Deck::new();
+        deck.cards.extend([Card::RED_JOKER, Card::BLACK_JOKER].iter().take(jokers));
+        deck.jokers = jokers;
+        deck
+    }
+
+    /// Shuffle the whole deck, including any dealt and discarded cards, with a fresh random seed
     pub fn shuffle(&mut self){
-        self.cards.shuffle(&mut rng());
+        self.shuffle_with_seed(rand::rng().random());
+    }
+
+    /// Shuffle the whole deck from its sorted order using the given seed, so the same seed always gives the same deck order
+    pub fn shuffle_with_seed(&mut self, seed: [u8; 32]){
+        self.cards = Deck::with_jokers(self.jokers).cards;
+        self.seed = seed;
+        self.rng = ChaCha20Rng::from_seed(seed);
+        fisher_yates(&mut self.cards, &mut self.rng);
         self.next_card_index = 0;
+        self.discards.clear();
+    }
+
+    /// The seed of the most recent shuffle, to be revealed once the hand is over
+    pub fn seed(&self) -> [u8; 32]{
+        self.seed
+    }
+
+    /// The SHA-256 commitment to the most recent shuffle's seed, as a hex string, to be published before the deal
+    pub fn commitment(&self) -> String{
+        commit_to_seed(&self.seed)
     }
 
-    /// Deal one card from the top of the deck
-    /// pub fn deal_one_card(&mut self) -> Option<Card> {
-    ///     self.cards.pop()
-    /// }
-    pub fn deal(&mut self) -> i32{
+    /// Deal one card from the top of the deck, shuffling the discards into a new stub if the deck has run out
+    /// 
+    /// Returns `None` once both the deck and the discards are empty.
+    pub fn deal(&mut self) -> Option<Card>{
+        if self.next_card_index as usize >= self.cards.len() {
+            if self.discards.is_empty() {
+                return None;
+            }
+            self.reshuffle_discards();
+        }
         let card = self.cards[self.next_card_index as usize];
         self.next_card_index += 1;
-        card
+        Some(card)
+    }
+
+    /// Put cards exchanged during a drawing round on the discard pile
+    pub fn discard(&mut self, cards: &[Card]){
+        self.discards.extend_from_slice(cards);
+    }
+
+    /// Move the discards to the end of the deck, shuffled, so they are dealt next
+    fn reshuffle_discards(&mut self){
+        let mut discards = std::mem::take(&mut self.discards);
+        fisher_yates(&mut discards, &mut self.rng);
+        // cards still held by players stay in the dealt part of the deck
+        self.cards.retain(|card| !discards.contains(card));
+        self.next_card_index = self.cards.len() as i32;
+        self.cards.extend(discards);
     }
 }
 
+/// Shuffle cards in place, drawing every swap from the given generator as described in the module documentation
+fn fisher_yates(cards: &mut [Card], rng: &mut ChaCha20Rng){
+    for i in (1..cards.len()).rev() {
+        let bound = i as u64 + 1;
+        let zone = (1u64 << 32) - (1u64 << 32) % bound;
+        let j = loop {
+            let value = rng.next_u32() as u64;
+            if value < zone {
+                break (value % bound) as usize;
+            }
+        };
+        cards.swap(i, j);
+    }
+}
+
+/// Hash a shuffle seed with SHA-256 and format it as a lowercase hex string
+pub fn commit_to_seed(seed: &[u8; 32]) -> String{
+    to_hex(&Sha256::digest(seed))
+}
+
+/// Format bytes as a lowercase hex string, used to publish seeds and commitments
+pub fn to_hex(bytes: &[u8]) -> String{
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn test_deck_creation() {
         let deck = Deck::new();
         assert_eq!(deck.cards.len(), 52);
         assert_eq!(deck.next_card_index, 0);
+        let unique_cards: HashSet<Card> = deck.cards.iter().cloned().collect();
+        assert_eq!(unique_cards.len(), 52);
+    }
+
+    #[test]
+    fn test_deck_with_jokers() {
+        let deck = Deck::with_jokers(0);
+        assert_eq!(deck.cards.len(), 52);
+
+        let deck = Deck::with_jokers(1);
+        assert_eq!(deck.cards.len(), 53);
+        assert_eq!(deck.cards.iter().filter(|card| card.is_joker()).count(), 1);
+
+        let deck = Deck::with_jokers(2);
+        let unique_cards: HashSet<Card> = deck.cards.iter().cloned().collect();
+        assert_eq!(unique_cards.len(), 54);
+        assert!(unique_cards.contains(&Card::RED_JOKER) && unique_cards.contains(&Card::BLACK_JOKER));
     }
 
     #[test]
@@ -54,23 +168,123 @@ mod tests {
         assert_eq!(deck.next_card_index, 0);
     }
 
+    #[test]
+    fn test_seeded_shuffle_is_reproducible() {
+        let mut first = Deck::new();
+        let mut second = Deck::new();
+        first.shuffle_with_seed([7; 32]);
+        second.deal();
+        second.shuffle_with_seed([7; 32]);
+        assert_eq!(first.cards, second.cards);
+
+        // the order only depends on the seed, not on how the deck was left
+        first.shuffle_with_seed([7; 32]);
+        assert_eq!(first.cards, second.cards);
+
+        second.shuffle_with_seed([8; 32]);
+        assert_ne!(first.cards, second.cards);
+    }
+
+    #[test]
+    fn test_seeded_shuffle_known_order() {
+        // pins the documented algorithm so clients re-deriving a deck get the same order
+        let mut deck = Deck::new();
+        deck.shuffle_with_seed([0; 32]);
+        let top: Vec<String> = deck.cards[..5].iter().map(|card| card.to_string()).collect();
+        assert_eq!(top, ["5h", "6s", "Jc", "5c", "Qc"]);
+    }
+
+    #[test]
+    fn test_commit_reveal() {
+        let mut deck = Deck::new();
+        deck.shuffle();
+        let commitment = deck.commitment();
+        assert_eq!(commitment.len(), 64);
+        assert_eq!(commit_to_seed(&deck.seed()), commitment);
+
+        let mut wrong_seed = deck.seed();
+        wrong_seed[0] ^= 1;
+        assert_ne!(commit_to_seed(&wrong_seed), commitment);
+
+        // SHA-256 of 32 zero bytes
+        assert_eq!(commit_to_seed(&[0; 32]), "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925");
+    }
+
+    #[test]
+    fn test_reshuffled_discards_follow_the_seed() {
+        let deal_after_reshuffle = || {
+            let mut deck = Deck::new();
+            deck.shuffle_with_seed([3; 32]);
+            let cards: Vec<Card> = (0..52).map(|_| deck.deal().unwrap()).collect();
+            deck.discard(&cards[..20]);
+            (0..20).map(|_| deck.deal().unwrap()).collect::<Vec<Card>>()
+        };
+        assert_eq!(deal_after_reshuffle(), deal_after_reshuffle());
+    }
+
     #[test]
     fn test_deal() {
         let mut deck = Deck::new();
         let first_card = deck.cards[0];
         let dealt_card = deck.deal();
-        assert_eq!(first_card, dealt_card);
+        assert_eq!(Some(first_card), dealt_card);
         assert_eq!(deck.next_card_index, 1);
     }
 
     #[test]
     fn test_deal_all_cards() {
         let mut deck = Deck::new();
-        for i in 0..52 {
-            let card = deck.deal();
-            assert_eq!(card, i);
+        for suit in Suit::ALL {
+            for rank in Rank::ALL {
+                let card = deck.deal();
+                assert_eq!(card, Some(Card::new(rank, suit)));
+            }
         }
         assert_eq!(deck.next_card_index, 52);
     }
-}
 
+    #[test]
+    fn test_deal_from_empty_deck() {
+        let mut deck = Deck::new();
+        for _ in 0..52 {
+            assert!(deck.deal().is_some());
+        }
+        assert_eq!(deck.deal(), None);
+        assert_eq!(deck.deal(), None);
+    }
+
+    #[test]
+    fn test_discards_are_reshuffled_into_stub() {
+        let mut deck = Deck::new();
+        deck.shuffle();
+        let mut held: Vec<Card> = (0..47).map(|_| deck.deal().unwrap()).collect();
+        let discarded: Vec<Card> = held.drain(..10).collect();
+        deck.discard(&discarded);
+
+        // the five cards left in the stub come first, then the ten discards
+        let mut dealt: Vec<Card> = (0..15).map(|_| deck.deal().unwrap()).collect();
+        assert_eq!(deck.deal(), None);
+        let mut redealt = dealt.split_off(5);
+        let mut expected = discarded.clone();
+        redealt.sort_by_key(|card| card.to_string());
+        expected.sort_by_key(|card| card.to_string());
+        assert_eq!(redealt, expected);
+
+        // no card still held by a player is dealt twice
+        assert!(dealt.iter().all(|card| !held.contains(card)));
+        assert_eq!(deck.cards.len(), 52);
+    }
+
+    #[test]
+    fn test_shuffle_restores_full_deck() {
+        let mut deck = Deck::new();
+        let cards: Vec<Card> = (0..52).map(|_| deck.deal().unwrap()).collect();
+        deck.discard(&cards[..5]);
+        deck.deal();
+        deck.shuffle();
+        assert!(deck.discards.is_empty());
+        let unique_cards: HashSet<Card> = (0..52).map(|_| deck.deal().unwrap()).collect();
+        assert_eq!(unique_cards.len(), 52);
+        assert_eq!(deck.deal(), None);
+    }
+}
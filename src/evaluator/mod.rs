@@ -0,0 +1,544 @@
+//! A module for scoring poker hands
+//! 
+//! This module contains the hand evaluators used at the showdown. Every evaluator returns a `HandValue`, which can be compared directly: a greater value is always the better hand, and equal values split the pot.
+//! 
+//! Five-card hands are ranked with Aces high, except in the wheel straight (A-2-3-4-5). Badugi hands are ranked with Aces low.
+//! 
+//! Jokers, and any ranks a variant declares wild, can stand in for any card. A wild card always plays as the best possible substitute, which makes five of a kind the strongest hand.
+use crate::card::{Card, Rank, Suit};
+use std::cmp::{Ordering, Reverse};
+use std::fmt;
+
+/// The category of a five-card poker hand, from weakest to strongest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HandCategory {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+    FiveOfAKind,
+}
+
+impl fmt::Display for HandCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            HandCategory::HighCard => "High Card",
+            HandCategory::OnePair => "One Pair",
+            HandCategory::TwoPair => "Two Pair",
+            HandCategory::ThreeOfAKind => "Three of a Kind",
+            HandCategory::Straight => "Straight",
+            HandCategory::Flush => "Flush",
+            HandCategory::FullHouse => "Full House",
+            HandCategory::FourOfAKind => "Four of a Kind",
+            HandCategory::StraightFlush => "Straight Flush",
+            HandCategory::FiveOfAKind => "Five of a Kind",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The score of a five-card poker hand.
+/// 
+/// `ranks` holds every rank that breaks a tie within the category, most significant first, with Aces high (2 to 14) and unused slots set to 0. For example two pair, Kings and Fives with a Jack kicker is `[13, 5, 11, 0, 0]`, and the wheel (A-2-3-4-5) is a straight with `[5, 0, 0, 0, 0]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HandRank {
+    pub category: HandCategory,
+    pub ranks: [i32; 5],
+}
+
+/// The score of a Badugi hand: the number of cards that play, then the playing ranks from highest to lowest with Aces low (1 to 13).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BadugiRank {
+    pub card_count: i32,
+    pub ranks: [i32; 4],
+}
+
+impl Ord for BadugiRank {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // more playing cards wins, then the lowest cards win
+        self.card_count
+            .cmp(&other.card_count)
+            .then_with(|| other.ranks.cmp(&self.ranks))
+    }
+}
+
+impl PartialOrd for BadugiRank {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A comparable hand score. Values are only compared within a single variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HandValue {
+    Poker(HandRank),
+    Badugi(BadugiRank),
+}
+
+/// The name of a rank from its value, Aces high from 2 to 14, or Aces low from 1 to 13 if `aces_low` is set.
+fn rank_name(value: i32, aces_low: bool) -> String {
+    Rank::ALL
+        .iter()
+        .find(|rank| if aces_low { rank.low_value() == value } else { rank.value() == value })
+        .map_or_else(|| value.to_string(), |rank| rank.name())
+}
+
+impl fmt::Display for HandRank {
+    /// Writes the category with the ranks that make it, such as `Full House (Kings over 5s)` or `Flush (Ace high)`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |i: usize| rank_name(self.ranks[i], false);
+        let plural = |i: usize| format!("{}s", name(i));
+        match self.category {
+            HandCategory::HighCard | HandCategory::Straight | HandCategory::Flush | HandCategory::StraightFlush => {
+                write!(f, "{} ({} high)", self.category, name(0))
+            }
+            HandCategory::OnePair | HandCategory::ThreeOfAKind | HandCategory::FourOfAKind | HandCategory::FiveOfAKind => {
+                write!(f, "{} ({})", self.category, plural(0))
+            }
+            HandCategory::TwoPair => write!(f, "{} ({} and {})", self.category, plural(0), plural(1)),
+            HandCategory::FullHouse => write!(f, "{} ({} over {})", self.category, plural(0), plural(1)),
+        }
+    }
+}
+
+impl fmt::Display for HandValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandValue::Poker(rank) => write!(f, "{}", rank),
+            HandValue::Badugi(rank) => write!(f, "{}-card Badugi ({} high)", rank.card_count, rank_name(rank.ranks[0], true)),
+        }
+    }
+}
+
+/// Scores exactly five natural cards. Use `get_wild_hand_type` for hands that may hold wild cards.
+pub fn get_hand_type(hand: &[Card]) -> HandRank {
+    assert!(hand.len() == 5);
+
+    // count each rank with Aces high, so ranks run from 2 to 14
+    let mut counts = [0; 15];
+    for card in hand {
+        counts[card.rank.value() as usize] += 1;
+    }
+
+    // group equal ranks, largest group first and higher ranks first within a group size
+    let mut groups = [(0, 0); 5];
+    let mut group_count = 0;
+    for rank in (2..15).rev() {
+        if counts[rank] > 0 {
+            groups[group_count] = (counts[rank], rank as i32);
+            group_count += 1;
+        }
+    }
+    groups[..group_count].sort_by_key(|group| Reverse(group.0));
+
+    let mut ranks = [0; 5];
+    for (i, group) in groups[..group_count].iter().enumerate() {
+        ranks[i] = group.1;
+    }
+
+    if group_count == 5 {
+        let flush = hand.iter().all(|card| card.suit == hand[0].suit);
+        let wheel = ranks == [14, 5, 4, 3, 2];
+        let straight = ranks[0] - ranks[4] == 4 || wheel;
+        let straight_ranks = [if wheel { 5 } else { ranks[0] }, 0, 0, 0, 0];
+
+        let (category, ranks) = match (straight, flush) {
+            (true, true) => (HandCategory::StraightFlush, straight_ranks),
+            (false, true) => (HandCategory::Flush, ranks),
+            (true, false) => (HandCategory::Straight, straight_ranks),
+            (false, false) => (HandCategory::HighCard, ranks),
+        };
+        return HandRank { category, ranks };
+    }
+
+    let category = match (groups[0].0, groups[1].0) {
+        (5, _) => HandCategory::FiveOfAKind,
+        (4, _) => HandCategory::FourOfAKind,
+        (3, 2) => HandCategory::FullHouse,
+        (3, _) => HandCategory::ThreeOfAKind,
+        (2, 2) => HandCategory::TwoPair,
+        _ => HandCategory::OnePair,
+    };
+    HandRank { category, ranks }
+}
+
+/// Whether a card is wild: jokers always are, and so is any card whose rank is in `wild_ranks`.
+pub fn is_wild(card: &Card, wild_ranks: &[Rank]) -> bool {
+    card.is_joker() || wild_ranks.contains(&card.rank)
+}
+
+/// Scores exactly five cards where jokers and any card of a rank in `wild_ranks` stand in for whichever card makes the best hand.
+pub fn get_wild_hand_type(hand: &[Card], wild_ranks: &[Rank]) -> HandRank {
+    assert!(hand.len() == 5);
+
+    let (wild, natural): (Vec<Card>, Vec<Card>) = hand.iter().partition(|card| is_wild(card, wild_ranks));
+    if wild.is_empty() {
+        return get_hand_type(hand);
+    }
+
+    // when every natural card shares a rank, the wild cards make five of a kind (five Aces if every card is wild)
+    let first_rank = natural.first().map_or(Rank::Ace, |card| card.rank);
+    if natural.iter().all(|card| card.rank == first_rank) {
+        return HandRank {
+            category: HandCategory::FiveOfAKind,
+            ranks: [first_rank.value(), 0, 0, 0, 0],
+        };
+    }
+
+    // suits only matter for flushes, so the wild cards take the suit of the first natural card and try every rank
+    let suit = natural[0].suit;
+    let mut substitutes = vec![0; wild.len()];
+    let mut best_rank = None;
+    loop {
+        let mut candidate = natural.clone();
+        candidate.extend(substitutes.iter().map(|&i| Card::new(Rank::ALL[i], suit)));
+        let rank = get_hand_type(&candidate);
+        if best_rank.is_none_or(|best| rank > best) {
+            best_rank = Some(rank);
+        }
+
+        // step to the next combination of substitute ranks
+        let mut position = 0;
+        while position < substitutes.len() && substitutes[position] == Rank::ALL.len() - 1 {
+            substitutes[position] = 0;
+            position += 1;
+        }
+        if position == substitutes.len() {
+            break;
+        }
+        substitutes[position] += 1;
+    }
+    best_rank.unwrap()
+}
+
+/// Picks the strongest five-card hand out of five to seven cards, such as a player's hole cards together with the community cards.
+/// 
+/// Every five-card combination is scored without allocating, so a seven-card hand costs 21 calls to `get_hand_type`. This is fast enough to score many thousands of hands per second for equity simulations.
+/// 
+/// # Returns
+/// The rank of the best hand, along with the five cards that make it up.
+pub fn get_best_hand(cards: &[Card]) -> (HandRank, [Card; 5]) {
+    assert!((5..=7).contains(&cards.len()));
+
+    let mut best_hand = [cards[0], cards[1], cards[2], cards[3], cards[4]];
+    let mut best_rank = get_hand_type(&best_hand);
+    let n = cards.len();
+    for a in 0..n {
+        for b in (a + 1)..n {
+            for c in (b + 1)..n {
+                for d in (c + 1)..n {
+                    for e in (d + 1)..n {
+                        let hand = [cards[a], cards[b], cards[c], cards[d], cards[e]];
+                        let rank = get_hand_type(&hand);
+                        if rank > best_rank {
+                            best_rank = rank;
+                            best_hand = hand;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    (best_rank, best_hand)
+}
+
+/// Scores a four-card Badugi hand. Only the largest subset of cards with distinct ranks and
+/// distinct suits plays, aces are low, and the lowest hand wins.
+pub fn get_badugi_hand_type(hand: &[Card]) -> BadugiRank {
+    assert!(hand.len() == 4);
+
+    let mut best_hand: Option<BadugiRank> = None;
+    for mask in 1..(1 << hand.len()) {
+        let subset: Vec<Card> = (0..hand.len())
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| hand[i])
+            .collect();
+
+        // aces are low in Badugi, so ranks run from 1 (Ace) to 13 (King)
+        let mut ranks: Vec<i32> = subset.iter().map(|card| card.rank.low_value()).collect();
+        let mut suits: Vec<Suit> = subset.iter().map(|card| card.suit).collect();
+        ranks.sort_unstable_by(|a, b| b.cmp(a));
+        suits.sort_unstable();
+        let mut distinct_ranks = ranks.clone();
+        distinct_ranks.dedup();
+        suits.dedup();
+        if distinct_ranks.len() != subset.len() || suits.len() != subset.len() {
+            continue;
+        }
+
+        let mut hand_ranks = [0; 4];
+        hand_ranks[..ranks.len()].copy_from_slice(&ranks);
+        let hand_type = BadugiRank { card_count: subset.len() as i32, ranks: hand_ranks };
+        if best_hand.is_none_or(|best| hand_type > best) {
+            best_hand = Some(hand_type);
+        }
+    }
+    best_hand.unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+    use crate::card::Rank;
+    use std::collections::{HashMap, HashSet};
+
+    /// Parses a space-separated list of cards in short notation.
+    fn cards(hand: &str) -> Vec<Card> {
+        hand.split_whitespace().map(|card| card.parse().unwrap()).collect()
+    }
+
+    /// Every card in a standard 52-card deck.
+    fn full_deck() -> Vec<Card> {
+        Suit::ALL
+            .iter()
+            .flat_map(|&suit| Rank::ALL.iter().map(move |&rank| Card::new(rank, suit)))
+            .collect()
+    }
+
+    #[test]
+    fn test_all_five_card_hands() {
+        let mut category_counts: HashMap<HandCategory, i32> = HashMap::new();
+        let mut distinct_ranks: HashSet<HandRank> = HashSet::new();
+        let deck = full_deck();
+        for a in 0..52 {
+            for b in (a + 1)..52 {
+                for c in (b + 1)..52 {
+                    for d in (c + 1)..52 {
+                        for e in (d + 1)..52 {
+                            let rank = get_hand_type(&[deck[a], deck[b], deck[c], deck[d], deck[e]]);
+                            *category_counts.entry(rank.category).or_insert(0) += 1;
+                            distinct_ranks.insert(rank);
+                        }
+                    }
+                }
+            }
+        }
+
+        assert_eq!(category_counts.values().sum::<i32>(), 2_598_960);
+        assert_eq!(category_counts[&HandCategory::StraightFlush], 40);
+        assert_eq!(category_counts[&HandCategory::FourOfAKind], 624);
+        assert_eq!(category_counts[&HandCategory::FullHouse], 3_744);
+        assert_eq!(category_counts[&HandCategory::Flush], 5_108);
+        assert_eq!(category_counts[&HandCategory::Straight], 10_200);
+        assert_eq!(category_counts[&HandCategory::ThreeOfAKind], 54_912);
+        assert_eq!(category_counts[&HandCategory::TwoPair], 123_552);
+        assert_eq!(category_counts[&HandCategory::OnePair], 1_098_240);
+        assert_eq!(category_counts[&HandCategory::HighCard], 1_302_540);
+        // every distinct hand strength is told apart by its kickers
+        assert_eq!(distinct_ranks.len(), 7_462);
+    }
+
+    #[test]
+    fn test_all_five_card_hands_with_a_joker() {
+        let mut category_counts: HashMap<HandCategory, i32> = HashMap::new();
+        let mut deck = full_deck();
+        deck.push(Card::RED_JOKER);
+        for a in 0..53 {
+            for b in (a + 1)..53 {
+                for c in (b + 1)..53 {
+                    for d in (c + 1)..53 {
+                        for e in (d + 1)..53 {
+                            let rank = get_wild_hand_type(&[deck[a], deck[b], deck[c], deck[d], deck[e]], &[]);
+                            *category_counts.entry(rank.category).or_insert(0) += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        assert_eq!(category_counts.values().sum::<i32>(), 2_869_685);
+        assert_eq!(category_counts[&HandCategory::FiveOfAKind], 13);
+        assert_eq!(category_counts[&HandCategory::StraightFlush], 204);
+        assert_eq!(category_counts[&HandCategory::FourOfAKind], 3_120);
+        assert_eq!(category_counts[&HandCategory::FullHouse], 6_552);
+        assert_eq!(category_counts[&HandCategory::Flush], 7_804);
+        assert_eq!(category_counts[&HandCategory::Straight], 20_532);
+        assert_eq!(category_counts[&HandCategory::ThreeOfAKind], 137_280);
+        assert_eq!(category_counts[&HandCategory::TwoPair], 123_552);
+        assert_eq!(category_counts[&HandCategory::OnePair], 1_268_088);
+        assert_eq!(category_counts[&HandCategory::HighCard], 1_302_540);
+    }
+
+    #[test]
+    fn test_wild_cards_play_as_best_substitute() {
+        // four Aces and a joker is five of a kind, which beats a royal flush
+        let five_aces = get_wild_hand_type(&cards("As Ad Ah Ac RJ"), &[]);
+        let royal_flush = get_wild_hand_type(&cards("As Ks Qs Js Ts"), &[]);
+        assert_eq!(five_aces.category, HandCategory::FiveOfAKind);
+        assert!(five_aces > royal_flush);
+
+        // a joker fills the gap in a straight flush
+        let straight_flush = get_wild_hand_type(&cards("9h 8h 6h 5h BJ"), &[]);
+        assert_eq!(straight_flush.category, HandCategory::StraightFlush);
+        assert_eq!(straight_flush.ranks, [9, 0, 0, 0, 0]);
+
+        // a joker in a flush plays as the highest missing card
+        let flush = get_wild_hand_type(&cards("9h 7h 4h 2h RJ"), &[]);
+        assert_eq!(flush.category, HandCategory::Flush);
+        assert_eq!(flush.ranks, [14, 9, 7, 4, 2]);
+
+        // every card wild is five Aces
+        let all_wild = get_wild_hand_type(&cards("2h 2d 2s RJ BJ"), &[Rank::Two]);
+        assert_eq!(all_wild.ranks, [14, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_deuces_wild() {
+        let deuces = [Rank::Two];
+        let quads = get_wild_hand_type(&cards("2h 2d Ks Kc 7h"), &deuces);
+        assert_eq!(quads.category, HandCategory::FourOfAKind);
+        assert_eq!(quads.ranks, [13, 7, 0, 0, 0]);
+
+        // without deuces wild the same hand is only two pair
+        assert_eq!(get_wild_hand_type(&cards("2h 2d Ks Kc 7h"), &[]).category, HandCategory::TwoPair);
+
+        // a deuce turns a pair into three of a kind rather than two pair
+        let trips = get_wild_hand_type(&cards("2c 9d 9s 5c 4h"), &deuces);
+        assert_eq!(trips.category, HandCategory::ThreeOfAKind);
+        assert_eq!(trips.ranks, [9, 5, 4, 0, 0]);
+    }
+
+    #[test]
+    fn test_two_pair_beats_one_pair_and_loses_to_trips() {
+        let two_pair = get_hand_type(&cards("5h 5d 8s 8c Th"));
+        let one_pair = get_hand_type(&cards("Ah Ad Ks Qc Jh"));
+        let trips = get_hand_type(&cards("2h 2d 2s 6c Th"));
+        assert_eq!(two_pair.category, HandCategory::TwoPair);
+        assert_eq!(two_pair.ranks, [8, 5, 10, 0, 0]);
+        assert!(two_pair > one_pair);
+        assert!(trips > two_pair);
+    }
+
+    #[test]
+    fn test_wheel_straight() {
+        let wheel = get_hand_type(&cards("Ah 2d 3s 4c 5h"));
+        assert_eq!(wheel.category, HandCategory::Straight);
+        assert_eq!(wheel.ranks, [5, 0, 0, 0, 0]);
+
+        // the wheel is the lowest straight
+        let six_high = get_hand_type(&cards("2h 3d 4s 5c 6h"));
+        assert!(six_high > wheel);
+
+        // a suited wheel is a straight flush
+        let steel_wheel = get_hand_type(&cards("As 2s 3s 4s 5s"));
+        assert_eq!(steel_wheel.category, HandCategory::StraightFlush);
+    }
+
+    #[test]
+    fn test_kickers_break_ties() {
+        // same pair of Kings, the Queen kicker beats the Jack kicker
+        let queen_kicker = get_hand_type(&cards("Kh Kd Qs 3c 2h"));
+        let jack_kicker = get_hand_type(&cards("Ks Kc Jh 3d 2s"));
+        assert!(queen_kicker > jack_kicker);
+
+        // the last kicker still counts
+        let four_low = get_hand_type(&cards("Ah Kd Js 9c 4h"));
+        let three_low = get_hand_type(&cards("Ad Ks Jc 9h 3d"));
+        assert!(four_low > three_low);
+
+        // identical ranks in different suits split the pot
+        let hearts_pair = get_hand_type(&cards("6h 6d 8s Tc Qh"));
+        let spades_pair = get_hand_type(&cards("6s 6c 8h Td Qs"));
+        assert_eq!(hearts_pair, spades_pair);
+    }
+
+    #[test]
+    fn test_best_hand_of_seven() {
+        // the flush on board beats the pair in the hole
+        let (best, best_cards) = get_best_hand(&cards("6h 6d 2s 5s 9s Js Ks"));
+        assert_eq!(best.category, HandCategory::Flush);
+        assert_eq!(best.ranks, [13, 11, 9, 5, 2]);
+        assert_eq!(best_cards.to_vec(), cards("2s 5s 9s Js Ks"));
+    }
+
+    #[test]
+    fn test_best_hand_of_six() {
+        // the last card completes a King-high straight
+        let (best, best_cards) = get_best_hand(&cards("9h Td Js Qc 4h Kd"));
+        assert_eq!(best.category, HandCategory::Straight);
+        assert_eq!(best.ranks, [13, 0, 0, 0, 0]);
+        assert!(!best_cards.contains(&"4h".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_best_hand_matches_its_cards() {
+        let mut rng = StdRng::seed_from_u64(421);
+        let mut deck = full_deck();
+        for _ in 0..2_000 {
+            deck.shuffle(&mut rng);
+            let cards = &deck[..7];
+            let (best, best_cards) = get_best_hand(cards);
+
+            // the returned cards come from the input and score the returned rank
+            assert!(best_cards.iter().all(|card| cards.contains(card)));
+            assert_eq!(get_hand_type(&best_cards), best);
+
+            // no five cards of the seven beat it, and the best of six never beats the best of seven
+            assert!(get_best_hand(&cards[..6]).0 <= best);
+            assert!(get_hand_type(&cards[..5]) <= best);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_best_hand_rejects_eight_cards() {
+        get_best_hand(&full_deck()[..8]);
+    }
+
+    #[test]
+    fn test_hand_names() {
+        let name = |hand: &str| HandValue::Poker(get_hand_type(&cards(hand))).to_string();
+        assert_eq!(name("Kh Kd Ks 5c 5h"), "Full House (Kings over 5s)");
+        assert_eq!(name("Kh Kd 5s 5c Jh"), "Two Pair (Kings and 5s)");
+        assert_eq!(name("6h 6d 2s 9c Jh"), "One Pair (6s)");
+        assert_eq!(name("Ah 2d 3s 4c 5h"), "Straight (5 high)");
+        assert_eq!(name("Ah 9h 3h 4h Th"), "Flush (Ace high)");
+        assert_eq!(name("Qh 9d 3s 4c Th"), "High Card (Queen high)");
+        let badugi = HandValue::Badugi(get_badugi_hand_type(&cards("Kh 3d 2s Ac")));
+        assert_eq!(badugi.to_string(), "4-card Badugi (King high)");
+    }
+
+    #[test]
+    fn test_badugi_counts_playing_cards() {
+        // A-2-3-4 in four suits is a four-card badugi
+        let badugi = get_badugi_hand_type(&cards("Ah 2d 3s 4c"));
+        assert_eq!(badugi.card_count, 4);
+
+        // a paired hand only plays three cards
+        let paired = get_badugi_hand_type(&cards("Ah Ad 3s 4c"));
+        assert_eq!(paired.card_count, 3);
+
+        // four cards of one suit only play one card
+        let suited = get_badugi_hand_type(&cards("Ah 2h 3h 4h"));
+        assert_eq!(suited.card_count, 1);
+        assert!(badugi > paired && paired > suited);
+    }
+
+    #[test]
+    fn test_badugi_lowest_hand_wins() {
+        // A-2-3-4 beats 2-3-4-5
+        let wheel = get_badugi_hand_type(&cards("Ah 2d 3s 4c"));
+        let five_high = get_badugi_hand_type(&cards("2h 3d 4s 5c"));
+        assert!(wheel > five_high);
+
+        // K-3-2-A loses to Q-J-10-9 because the highest card is compared first
+        let king_high = get_badugi_hand_type(&cards("Kh 3d 2s Ac"));
+        let queen_high = get_badugi_hand_type(&cards("Qh Jd Ts 9c"));
+        assert!(queen_high > king_high);
+    }
+
+    #[test]
+    fn test_badugi_keeps_best_subset() {
+        // K and 2 of hearts clash, so the hand plays 4-3-2 and drops the King
+        let hand = get_badugi_hand_type(&cards("Kh 2h 3s 4c"));
+        assert_eq!(hand, BadugiRank { card_count: 3, ranks: [4, 3, 2, 0] });
+    }
+}
@@ -7,31 +7,29 @@
 //! The game state machine is implemented as a series of async functions that handle the game logic, such as dealing cards, betting rounds, and showdowns.
 //! 
 //! The game state machine is driven by player input, which is received via WebSocket messages. The game state machine processes the input and sends messages back to the players. 
-use super::*;
+use crate::betting::{next_active_seat, next_positions, Action, BetLimit, BettingRound, Event, ForcedBets, Seat};
+use crate::card::Card;
+use crate::clock::{next_warning, ActionClock, Turn};
+use crate::database::{Database, GameResult, RecordedAction, STARTING_WALLET};
+use crate::deck::to_hex;
 use crate::Deck;
-use futures_util::future::{ready, Join};
+use crate::pot::{award_pots, build_pots, split_pot, Contribution};
+use crate::protocol::{self, ActionKind, Inbox, Outbox, PotResult, Session};
+use crate::variant::{FiveCardDraw, Phase, PokerVariant};
 use sqlx::SqlitePool;
 use std::sync::Arc;
-use tokio::sync::{mpsc, mpsc::UnboundedSender, Mutex};
-use warp::{filters::ws::WebSocket, ws::Message};
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use warp::ws::Message;
 // use warp::filters::ws::SplitStream;
 
 // Lobby attribute definitions
 pub const MAX_PLAYER_COUNT: i32 = 5;
-const EMPTY: i32 = -1;
 pub const JOINABLE: i32 = 0;
 pub const START_OF_ROUND: i32 = 1;
-const ANTE: i32 = 2;
-const DEAL_CARDS: i32 = 3;
-const FIRST_BETTING_ROUND: i32 = 4;
-const DRAW: i32 = 5;
-const SECOND_BETTING_ROUND: i32 = 6;
 const SHOWDOWN: i32 = 7;
-const END_OF_ROUND: i32 = 8;
-const UPDATE_DB: i32 = 9;
 
 // Player state definitions
-pub const READY: i32 = 0;
 const FOLDED: i32 = 1;
 const ALL_IN: i32 = 2;
 const CHECKED: i32 = 3;
@@ -48,6 +46,7 @@ pub const SERVER_FULL: i32 = 102;
 pub const GAME_LOBBY_EMPTY: i32 = 103;
 pub const GAME_LOBBY_NOT_EMPTY: i32 = 104;
 pub const GAME_LOBBY_FULL: i32 = 105;
+pub const GAME_IN_PROGRESS: i32 = 106;
 
 
 // Define Player struct
@@ -55,16 +54,19 @@ pub const GAME_LOBBY_FULL: i32 = 105;
 pub struct Player {
     pub name: String,
     pub id: String,
-    pub hand: Vec<i32>,
+    pub hand: Vec<Card>,
     pub wallet: i32,
-    pub tx: mpsc::UnboundedSender<Message>,
-    pub rx: Arc<Mutex<SplitStream<warp::ws::WebSocket>>>,
+    pub tx: Outbox,
+    pub rx: Arc<Mutex<Inbox>>,
+    pub session: Session, // lets a new connection take over this player's session
     pub state: i32,
     pub current_bet: i32,
+    pub total_bet: i32, // chips put into the pot over the whole hand, used to build side pots
     pub dealer: bool,
     pub ready: bool,
-    pub games_played: i32,
-    pub games_won: i32,
+    pub time_bank_used: bool,
+    pub games_played: i32, // games not yet added to the database
+    pub games_won: i32, // wins not yet added to the database
     pub lobby: Arc<Mutex<Lobby>>,
 }
 
@@ -72,7 +74,7 @@ impl Player {
     pub async fn get_player_input(&mut self) -> String {
         let mut return_string: String = "".to_string();
         let mut rx = self.rx.lock().await;
-        while let Some(result) = rx.next().await {
+        if let Some(result) = rx.next_message().await {
             match result {
                 Ok(msg) => {
                     if msg.is_close() {
@@ -82,19 +84,7 @@ impl Player {
                             IN_GAME => {
                                 self.state = IN_LOBBY;
                             }
-                            ANTE => {
-                                self.state = FOLDED;
-                            }
-                            DEAL_CARDS => {
-                                self.state = FOLDED;
-                            }
-                            FIRST_BETTING_ROUND => {
-                                self.state = FOLDED;
-                            }
-                            DRAW => {
-                                self.state = FOLDED;
-                            }
-                            SECOND_BETTING_ROUND => {
+                            CHECKED | CALLED | RAISED | ALL_IN => {
                                 self.state = FOLDED;
                             }
                             _ => {
@@ -118,7 +108,57 @@ impl Player {
                 }
             }
         }
-        return return_string;
+        return_string
+    }
+
+    /// Waits for the player's input until `deadline`, sending them countdown warnings as it gets close.
+    ///
+    /// # Returns
+    /// * `Some(String)` - The input, as returned by `get_player_input`.
+    /// * `None` - If the deadline passed first.
+    pub async fn get_player_input_before(&mut self, deadline: Instant) -> Option<String> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            // wake up for the next warning, or at the deadline once every warning has been sent
+            let warning = next_warning(remaining);
+            let wake_at = deadline - Duration::from_secs(warning.unwrap_or(0));
+            match tokio::time::timeout_at(wake_at, self.get_player_input()).await {
+                Ok(input) => return Some(input),
+                Err(_) => {
+                    if let Some(seconds) = warning {
+                        let _ = self.tx.send(Message::text(format!("{} seconds left to act.", seconds)));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Waits for the player's input on their turn, drawing on their time bank if their clock runs out.
+    ///
+    /// # Returns
+    /// * `String` - The input, or `"Timeout"` if the player ran out of time.
+    pub async fn get_turn_input(&mut self, turn: &mut Turn) -> String {
+        loop {
+            let Some(deadline) = turn.deadline() else {
+                return self.get_player_input().await;
+            };
+            if let Some(input) = self.get_player_input_before(deadline).await {
+                return input;
+            }
+            // the time bank can only be used once per hand
+            if !self.time_bank_used {
+                if let Some(seconds) = turn.use_time_bank() {
+                    self.time_bank_used = true;
+                    let _ = self.tx.send(Message::text(format!("Time bank activated: {} extra seconds.", seconds)));
+                    continue;
+                }
+            }
+            let _ = self.tx.send(Message::text("You ran out of time."));
+            return "Timeout".to_string();
+        }
     }
 
     pub async fn player_join_lobby(
@@ -130,7 +170,7 @@ impl Player {
         let lobby_names_and_status = server_lobby.lock().await.lobby_names_and_status.lock().await.clone();
         println!("Lobby name entered: {}", lobby_name);
         let mut found = false;
-        for (name, status) in lobby_names_and_status {
+        for (name, status, _) in lobby_names_and_status {
             if name == lobby_name && status == JOINABLE {
                 found = true;
                 break;
@@ -142,6 +182,11 @@ impl Player {
                 if let Ok(mut lobby_guard) = lobby_guard {
                     if lobby_guard.name == lobby_name {
                         println!("reached2");
+                        // the wallet may have changed since this copy was made, by a game or a reset of the stats
+                        match Database::new(lobby_guard.game_db.clone()).get_player_wallet(&self.name).await {
+                            Ok(wallet) => self.wallet = wallet,
+                            Err(e) => eprintln!("Failed to read the wallet of {}: {}", self.name, e),
+                        }
                         lobby_guard.add_player(self.clone()).await;
                         self.lobby = lobby.clone();
                         return SUCCESS;
@@ -161,56 +206,62 @@ pub struct Lobby {
     // Use Arc<Mutex<...>> so the Lobby struct can #[derive(Clone)]
     pub players: Arc<Mutex<Vec<Player>>>,
     pub lobbies: Arc<Mutex<Vec<Arc<Mutex<Lobby>>>>>,
-    pub lobby_names_and_status: Arc<Mutex<Vec<(String, i32)>>>, // store lobby names and their statuses
+    pub lobby_names_and_status: Arc<Mutex<Vec<(String, i32, String)>>>, // store lobby names, their statuses and variants
     pub game_db: SqlitePool,
     deck: Deck,
+    community_cards: Vec<Card>,
     pub pot: i32,
     pub current_player_count: i32,
     pub max_player_count: i32,
     pub game_state: i32,
+    pub variant: Arc<dyn PokerVariant>,
+    pub forced_bets: ForcedBets,
+    pub bet_limit: BetLimit,
+    pub action_clock: Option<ActionClock>, // None waits for players forever
+    pub button: i32, // seat of the dealer button, -1 until the first hand
     pub first_betting_player: i32,
+    actions: Vec<RecordedAction>, // every betting move of the current game, recorded with its result
 }
 
 impl Lobby {
-    pub async fn new(player_count: Option<i32>, lobby_name: String) -> Self {
+    pub async fn new(player_count: Option<i32>, lobby_name: String, variant: Option<Arc<dyn PokerVariant>>) -> Self {
+        Self::with_db(player_count, lobby_name, variant, SqlitePool::connect("sqlite://poker.db").await.unwrap())
+    }
+
+    /// Creates a lobby that keeps its records in the given database instead of `poker.db`.
+    ///
+    /// # Arguments
+    /// * `player_count` - The most players the lobby seats, `MAX_PLAYER_COUNT` if `None`.
+    /// * `lobby_name` - The name of the lobby.
+    /// * `variant` - The poker variant played in the lobby, five card draw if `None`.
+    /// * `game_db` - The database the lobby reads wallets from and records its games in.
+    ///
+    /// # Returns
+    /// * `Lobby` - An empty, joinable lobby.
+    pub fn with_db(player_count: Option<i32>, lobby_name: String, variant: Option<Arc<dyn PokerVariant>>, game_db: SqlitePool) -> Self {
+        let variant = variant.unwrap_or_else(|| Arc::new(FiveCardDraw));
         Self {
             name: lobby_name,
             players: Arc::new(Mutex::new(Vec::new())),
             lobbies: Arc::new(Mutex::new(Vec::new())),
             lobby_names_and_status: Arc::new(Mutex::new(Vec::new())),
-            deck: Deck::new(),
+            deck: Deck::with_jokers(variant.jokers()),
+            community_cards: Vec::new(),
             current_player_count: 0,
             max_player_count: player_count.unwrap_or(MAX_PLAYER_COUNT),
             pot: 0,
             game_state: JOINABLE,
+            forced_bets: variant.forced_bets(),
+            bet_limit: BetLimit::NoLimit,
+            action_clock: Some(ActionClock::default()),
+            variant,
+            button: -1,
             first_betting_player: 0,
-            game_db: SqlitePool::connect("sqlite://poker.db").await.unwrap(),
+            actions: Vec::new(),
+            game_db,
         }
     }
 
-    pub async fn listen_for_messages(
-        &self,
-        mut game_rx: mpsc::UnboundedReceiver<String>,
-    ) -> String {
-        while let Some(message) = game_rx.recv().await {
-            println!("Received message: {}", message);
-            return message;
-        }
-        "".to_string()
-    }
-
-    pub async fn increment_player_count(&mut self) {
-        self.current_player_count += 1;
-    }
-
-    pub async fn decrement_player_count(&mut self) {
-        self.current_player_count -= 1;
-    }
-
-    pub async fn get_player_count(&self) -> i32 {
-        self.current_player_count
-    }
-
     pub async fn add_player(&mut self, mut player: Player) {
         let mut players = self.players.lock().await;
         player.state = IN_LOBBY;
@@ -225,6 +276,12 @@ impl Lobby {
 
     pub async fn remove_player(&mut self, username: String) -> i32 {
         let mut players = self.players.lock().await;
+        if let Some(seat) = players.iter().position(|p| p.name == username) {
+            // keep the button on the same player, or if they left, pass it on from their empty seat next hand
+            if seat as i32 <= self.button {
+                self.button -= 1;
+            }
+        }
         players.retain(|p| p.name != username);
         let players_tx = players.iter().map(|p| p.tx.clone()).collect::<Vec<_>>();
         self.lobby_wide_send(players_tx, format!("{} has disconnected from {}.", username, self.name)).await;
@@ -243,8 +300,13 @@ impl Lobby {
         lobbies.push(lobby.clone());
         // push lobby name onto the tuple vec
         let lobby_name = lobby.lock().await.name.clone();
-        let lobby_status = lobby.lock().await.game_state.clone();
-        self.lobby_names_and_status.lock().await.push((lobby_name, lobby_status));
+        let lobby_status = lobby.lock().await.game_state;
+        let lobby_variant = {
+            let lobby = lobby.lock().await;
+            let clock = lobby.action_clock.map_or("no clock".to_string(), |clock| clock.to_string());
+            format!("{}, {}, {}, {}", lobby.variant.name(), lobby.forced_bets, lobby.bet_limit, clock)
+        };
+        self.lobby_names_and_status.lock().await.push((lobby_name, lobby_status, lobby_variant));
     }
 
     pub async fn remove_lobby(&self, lobby_name: String) {
@@ -262,13 +324,51 @@ impl Lobby {
         }
     }
 
-    pub async fn get_lobby_names_and_status(&self) -> Vec<(String, i32)> {
+    /// Resets the game numbering and every player's stats, both in the database and for connected players, then tells everyone on the server.
+    /// 
+    /// Called on the server lobby. The reset is refused while any game lobby is playing or about to start a game; every game lobby stays locked until the reset is done, so no game can start part way through.
+    /// 
+    /// # Returns
+    /// * `SUCCESS` - If everything was reset.
+    /// * `GAME_IN_PROGRESS` - If a game is being played, in which case nothing is changed.
+    /// * `FAILED` - If the database could not be reset, in which case nothing is changed.
+    pub async fn reset_history(&self) -> i32 {
+        let lobbies = self.lobbies.lock().await.clone();
+        let mut guards = Vec::new();
+        for lobby in lobbies.iter() {
+            // a lobby stays locked for the whole game
+            match lobby.try_lock() {
+                Ok(guard) if guard.game_state == JOINABLE || guard.game_state == GAME_LOBBY_FULL => guards.push(guard),
+                _ => return GAME_IN_PROGRESS,
+            }
+        }
+        if let Err(e) = Database::new(self.game_db.clone()).reset_history().await {
+            eprintln!("Failed to reset the game history: {}", e);
+            return FAILED;
+        }
+        let reset = |players: &mut Vec<Player>| {
+            for player in players.iter_mut() {
+                player.wallet = STARTING_WALLET;
+                player.games_played = 0;
+                player.games_won = 0;
+            }
+        };
+        reset(&mut *self.players.lock().await);
+        for guard in guards.iter() {
+            reset(&mut *guard.players.lock().await);
+        }
+        drop(guards);
+        self.broadcast("An administrator has reset the game numbering. Every player's games, wins and wallet have been reset.".to_string()).await;
+        SUCCESS
+    }
+
+    pub async fn get_lobby_names_and_status(&self) -> Vec<(String, i32, String)> {
         self.lobby_names_and_status.lock().await.clone()
     }
 
     pub async fn lobby_exists(&self, lobby_name: String) -> bool {
         let lobby_names_and_status = self.lobby_names_and_status.lock().await;
-        for (name, _) in lobby_names_and_status.iter() {
+        for (name, _, _) in lobby_names_and_status.iter() {
             if name == &lobby_name {
                 return true;
             }
@@ -280,7 +380,7 @@ impl Lobby {
         let players = self.players.lock().await;
         let message = players
             .iter()
-            .map(|p| p.name.clone())
+            .map(|p| if p.dealer { format!("{} (dealer)", p.name) } else { p.name.clone() })
             .collect::<Vec<String>>()
             .join("\n");
         message
@@ -303,13 +403,21 @@ impl Lobby {
         }
     }
 
+    /// Sends an event to every player in the lobby, rendered for each player's protocol.
+    pub async fn broadcast_event(&self, event: protocol::Event) {
+        let players = self.players.lock().await;
+        let players_tx = players.iter().map(|p| p.tx.clone()).collect::<Vec<_>>();
+        drop(players);
+        self.lobby_wide_event(players_tx, event).await;
+    }
+
     pub async fn lobby_wide_send(
         &self,
-        players_tx: Vec<UnboundedSender<Message>>,
+        players_tx: Vec<Outbox>,
         message: String,
     ) {
         let mut tasks = Vec::new();
-        for tx in players_tx.iter().cloned() {
+        for tx in players_tx {
             let msg = Message::text(message.clone());
             tasks.push(tokio::spawn(async move {
                 let _ = tx.send(msg);
@@ -321,6 +429,13 @@ impl Lobby {
         }
     }
 
+    /// Sends an event to the given players, rendered for each player's protocol.
+    pub async fn lobby_wide_event(&self, players_tx: Vec<Outbox>, event: protocol::Event) {
+        for tx in players_tx {
+            let _ = tx.send_event(&event);
+        }
+    }
+
     pub async fn ready_up(&self, username: String) -> (i32, i32) {
         let mut players = self.players.lock().await;
         // self.broadcast(format!("{} is ready!", username)).await;
@@ -336,15 +451,19 @@ impl Lobby {
         let players_tx = players.iter().map(|p| p.tx.clone()).collect::<Vec<_>>();
         self.lobby_wide_send(players_tx, format!("{} is ready!", username))
             .await;
-        return (ready_player_count, self.current_player_count);
+        (ready_player_count, self.current_player_count)
     }
 
     async fn deal_cards(&mut self) {
         let mut players = self.players.lock().await;
-        for _ in 0..5 {
+        let hand_size = self.variant.hand_size();
+        for _ in 0..hand_size {
             for player in players.iter_mut() {
                 if player.state != FOLDED {
-                    player.hand.push(self.deck.deal());
+                    match self.deck.deal() {
+                        Some(card) => player.hand.push(card),
+                        None => eprintln!("Deck ran out of cards while dealing to {}.", player.name),
+                    }
                 }
             }
         }
@@ -361,226 +480,256 @@ impl Lobby {
             .collect::<Vec<_>>(); // get all hands
         self.display_hand(players_tx.clone(), players_hands.clone())
             .await;
+        self.publish_table_state(&players, None);
     }
 
-    async fn betting_round(&mut self, round: i32) {
-        let mut players = self.players.lock().await;
-        if players.len() == 1 {
-            // only one player left, move on
-            return;
+    /// Records the table as each player sees it, so a player who reconnects can be caught up on the hand.
+    ///
+    /// # Arguments
+    /// * `players` - The players in the lobby.
+    /// * `to_act` - The name of the player whose turn it is, if any.
+    fn publish_table_state(&self, players: &[Player], to_act: Option<&str>) {
+        for player in players {
+            player.session.set_table_state(Some(protocol::Event::TableState {
+                lobby: self.name.clone(),
+                hand: player.hand.clone(),
+                board: self.community_cards.clone(),
+                pot: self.pot,
+                to_act: to_act.map(str::to_string),
+            }));
         }
-        let players_tx = players.iter().map(|p| p.tx.clone()).collect::<Vec<_>>();
-        println!("Current round: {}", round);
-        // ensure all players have current_bet set to 0
-
-        let mut current_player_index = self.first_betting_player;
-        let mut current_lobby_bet = 0; // resets to 0 every betting round
-        let mut players_remaining = self.current_player_count;
-        let mut folded_count = 0;
-        let mut all_folded = false;
+    }
 
-        for player in players.iter_mut() {
-            if player.state == FOLDED {
-                folded_count += 1;
+    async fn deal_community_cards(&mut self, count: usize) {
+        for _ in 0..count {
+            match self.deck.deal() {
+                Some(card) => self.community_cards.push(card),
+                None => eprintln!("Deck ran out of cards while dealing community cards."),
             }
         }
+        self.publish_table_state(&self.players.lock().await, None);
+        let mut translated_cards: String = Default::default();
+        for card in self.community_cards.iter() {
+            translated_cards.push_str(&format!("{}  {}\n", card.symbol(), card.name()));
+        }
+        self.broadcast(format!("Community cards:\n{}", translated_cards)).await;
+    }
 
-        if round == ANTE {
-            for player in players.iter_mut() {
-                if player.wallet > 10 {
-                    println!("Player {} antes 10.", player.name);
-                    self.pot += 10;
-                    player.wallet -= 10;
-                } else {
-                    player.state = FOLDED; // these guys cant play, spectator basically
-                }
-                player.games_played += 1;
+    async fn post_forced_bets(&mut self) {
+        let mut players = self.players.lock().await;
+        let players_tx = players.iter().map(|p| p.tx.clone()).collect::<Vec<_>>();
+        let required = match self.forced_bets {
+            ForcedBets::Ante(ante) => ante,
+            ForcedBets::Blinds { big, .. } => big,
+        };
+        for player in players.iter_mut() {
+            if player.wallet <= required {
+                player.state = FOLDED; // these guys cant play, spectator basically
             }
-            return;
+            player.games_played += 1;
         }
 
-        for player in players.iter_mut() {
-            player.current_bet = 0; // reset all players to 0
+        // move the button on to the next player who can play
+        let active = players.iter().map(|p| p.state != FOLDED).collect::<Vec<_>>();
+        let positions = next_positions(self.button, &active);
+        if let Some(positions) = positions {
+            self.button = positions.button as i32;
+            for (seat, player) in players.iter_mut().enumerate() {
+                player.dealer = seat == positions.button;
+            }
+            self.lobby_wide_send(players_tx.clone(), format!("{} has the dealer button.", players[positions.button].name)).await;
         }
 
-        while players_remaining > 0 {
-            println!("Current player index: {}", current_player_index);
-            let player = &mut players[current_player_index as usize];
-            if player.state == FOLDED || player.state == ALL_IN {
-                current_player_index = (current_player_index + 1) % self.current_player_count;
-                players_remaining -= 1;
-                continue;
+        match self.forced_bets {
+            ForcedBets::Ante(ante) => {
+                self.lobby_wide_send(players_tx.clone(), format!("Ante round!\nEveryone adds ${} to the pot.", ante)).await;
+                for player in players.iter_mut().filter(|p| p.state != FOLDED) {
+                    println!("Player {} antes {}.", player.name, ante);
+                    self.pot += ante;
+                    player.wallet -= ante;
+                    player.total_bet += ante;
+                }
+                self.first_betting_player = next_active_seat(self.button, &active).unwrap_or(0) as i32;
             }
-            let message = format!(
-                    "Choose an option:\n1. Check\n2. Raise\n3. Call\n4. Fold\n5. All-in\n\nYour amount to call: {}\nCurrent Pot: {}\nCurrent Wallet: {}",
-                    (current_lobby_bet - player.current_bet), self.pot, player.wallet
-                );
-            let _ = player.tx.send(Message::text(message));
-            loop {
-                let choice = player.get_player_input().await;
-                // println!("player input: {}", choice.clone());
+            ForcedBets::Blinds { small, big } => {
+                let Some(positions) = positions else {
+                    return;
+                };
+                for (seat, blind, blind_name) in [(positions.small_blind, small, "small"), (positions.big_blind, big, "big")] {
+                    let player = &mut players[seat];
+                    println!("Player {} posts the {} blind of {}.", player.name, blind_name, blind);
+                    self.pot += blind;
+                    player.wallet -= blind;
+                    // blinds count towards the player's bet in the first betting round
+                    player.current_bet += blind;
+                    player.total_bet += blind;
+                    self.lobby_wide_send(players_tx.clone(), format!("{} posts the {} blind of {}.", player.name, blind_name, blind)).await;
+                }
+                // the big blind acts last, so it gets the option to raise even if everyone just calls
+                self.first_betting_player = positions.first_to_act as i32;
+            }
+        }
+    }
 
-                match choice.as_str() {
-                    "1" => {
-                        if current_lobby_bet == 0 {
-                            player.state = CHECKED;
-                            println!("checked");
-                            // self.broadcast(format!("{} has checked.", player.name)).await;
-                            self.lobby_wide_send(
-                                players_tx.clone(),
-                                format!("{} has checked.", player.name),
-                            ).await;
-                            players_remaining -= 1; // on valid moves, decrement the amount of players to make a move
-                            break;
-                        } else {
-                            player
-                                .tx
-                                .send(Message::text(
-                                    "Invalid move: You can't check, there's a bet to call.",
-                                ))
-                                .ok();
-                        }
-                    }
-                    "2" => {
-                        // if player.state == RAISED {
-                        //     player.tx.send(Message::text("You've already raised this round.\nCall or fold.",)).ok();
-                        //     continue;
-                        // }
+    async fn betting_round(&mut self, round: usize, rounds: usize) {
+        let mut players = self.players.lock().await;
+        let players_tx = players.iter().map(|p| p.tx.clone()).collect::<Vec<_>>();
+        let seats = players
+            .iter()
+            .map(|p| Seat::new(p.wallet, p.current_bet, p.state == FOLDED))
+            .collect::<Vec<_>>();
+        let mut betting = BettingRound::new(
+            seats,
+            self.first_betting_player.max(0) as usize,
+            self.pot,
+            self.forced_bets.min_bet(),
+            self.bet_limit,
+            round,
+            rounds,
+        );
 
-                        let bet_diff = current_lobby_bet - player.current_bet;
-                        if current_lobby_bet > 0 {
-                            if player.wallet <= (current_lobby_bet - player.current_bet) {player.tx.send(Message::text("Invalid move: not enough cash to raise.\nCall or fold.",)).ok();
-                                continue;
+        while let Some(seat) = betting.to_act() {
+            println!("Current player index: {}", seat);
+            self.publish_table_state(&players, Some(&players[seat].name));
+            let player = &mut players[seat];
+            let _ = player.tx.send_event(&protocol::Event::YourTurn {
+                call_amount: betting.call_amount(seat),
+                pot: betting.pot(),
+                wallet: player.wallet,
+                raise_range: betting.raise_range(seat),
+            });
+            let mut turn = Turn::start(self.action_clock);
+            // when the clock runs out the player checks if they can, and folds otherwise
+            let timeout_action = if betting.call_amount(seat) == 0 { Action::Check } else { Action::Fold };
+            // an all-in is recorded as the call, bet or raise it amounts to
+            let table_bet = betting.table_bet();
+            let events = loop {
+                let choice = player.get_turn_input(&mut turn).await;
+                // a raise may come with its amount, as in "2 60" or "raise 60"
+                let (command, argument) = match choice.trim().split_once(' ') {
+                    Some((command, argument)) => (command, Some(argument.trim())),
+                    None => (choice.trim(), None),
+                };
+                let action = match command {
+                    "1" | "check" => Action::Check,
+                    "2" | "raise" | "bet" => {
+                        let Some((min_to, max_to)) = betting.raise_range(seat) else {
+                            // let the engine explain why the player can't raise
+                            if let Err(message) = betting.check_raise(seat, betting.table_bet()) {
+                                player.tx.send(Message::text(message)).ok();
                             }
-                            // print the minimum the player has to bet to stay in the game
-                            let _ = player.tx.send(Message::text(format!("Current minimum bet is: {}",bet_diff)));
-                        } else {
-                            let _ = player.tx.send(Message::text("Bet must be greater than 0."));
-                        }
-                        let _ = player.tx.send(Message::text(format!("Your current bet is: {}\nYour wallet balance: {}\nEnter your bet amount:", player.current_bet, player.wallet)));
-                        // let _ = player.tx.send(Message::text("Enter your bet amount:"));
-                        loop {
-                            let bet_amount = player.get_player_input().await;
-                            if let Ok(bet) = bet_amount.parse::<i32>() {
-                                // doesnt allow calling (all in case included) or raising if the player doesnt have enough money
-                                if bet > player.wallet || bet <= bet_diff || bet <= 0 {
-                                    player.tx.send(Message::text("Invalid raise.")).ok();
+                            continue;
+                        };
+                        let raise_to = match argument {
+                            Some(argument) => match argument.parse::<i32>() {
+                                Ok(raise_to) => Ok(raise_to),
+                                Err(_) => {
+                                    player.tx.send(Message::text("Invalid raise : not a number.")).ok();
+                                    continue;
+                                }
+                            },
+                            None => {
+                                if min_to == max_to {
+                                    let _ = player.tx.send(Message::text(format!("Your current bet is: {}\nYour wallet balance: {}\nYou can raise your bet to exactly {}.\nEnter the total you want to bet:", player.current_bet, player.wallet, min_to)));
                                 } else {
-                                    if bet == player.wallet {
-                                        player.state = ALL_IN;
-                                        // self.broadcast(format!("{} has gone all in!", player.name)).await;
-                                        self.lobby_wide_send(
-                                            players_tx.clone(),
-                                            format!("{} has gone all in!", player.name),
-                                        )
-                                        .await;
-                                    } else {
-                                        player.state = RAISED;
+                                    let _ = player.tx.send(Message::text(format!("Your current bet is: {}\nYour wallet balance: {}\nYou can raise your bet to between {} and {}.\nEnter the total you want to bet:", player.current_bet, player.wallet, min_to, max_to)));
+                                }
+                                loop {
+                                    let input = player.get_turn_input(&mut turn).await;
+                                    match input.parse::<i32>() {
+                                        Ok(raise_to) => break Ok(raise_to),
+                                        Err(_) if input == "Timeout" || input == "Disconnect" => break Err(input),
+                                        Err(_) => {
+                                            player.tx.send(Message::text("Invalid raise : not a number.")).ok();
+                                        }
                                     }
-                                    player.wallet -= bet;
-                                    player.current_bet += bet;
-                                    self.pot += bet;
-                                    current_lobby_bet = player.current_bet;
-
-                                    // self.broadcast(format!("{} has raised the pot to: {}", player.name, player.current_bet)).await;
-                                    self.lobby_wide_send(players_tx.clone(),format!("{} has raised the pot to: {}",player.name, self.pot),).await;
-                                    // reset the betting cycle so every player calls/raises the new max bet or folds
-                                    players_remaining = self.current_player_count - 1;
-                                    break;
                                 }
-                            } else {
-                                player.tx.send(Message::text("Invalid raise : not a number.")).ok();
                             }
-                        }
-                        break;
-                    }
-                    "3" => {
-                        if current_lobby_bet == 0 {
-                            player.tx.send(Message::text("Invalid move: no bet to call.")).ok();
-                            continue;
-                        }
-                        
-                        let call_amount = current_lobby_bet - player.current_bet;
-                        print!("call amount: {}", call_amount);
-                        println!("current lobby bet: {}", current_lobby_bet);
-                        // else if self.game_state == FIRST_BETTING_ROUND && current_lobby_bet == 10 {
-                        //     player.state = CHECKED;
-                        //     // self.broadcast(format!("{} has checked.", player.name)).await;
-                        //     self.lobby_wide_send(players_tx.clone(), format!("{} has checked.", player.name)).await;
-                        //     players_remaining -= 1;
-                        //     break;
-                        // }
-                        if call_amount > player.wallet {
-                            player.tx.send(Message::text("Invalid move: not enough cash.\nAll in or fold!",)).ok();
-                        } else {
-                            player.wallet -= call_amount;
-                            player.current_bet += call_amount;
-                            self.pot += call_amount;
-                            player.state = CALLED;
-                            // self.broadcast(format!("{} has called the bet.", player.name)).await;
-                            self.lobby_wide_send(players_tx.clone(),format!("{} has called the bet.", player.name),).await;
-                            players_remaining -= 1;
-                            break;
-                        }
-                    }
-                    "4" => {
-                        player.state = FOLDED;
-                        // self.broadcast(format!("{} has folded.", player.name)).await;
-                        self.lobby_wide_send(players_tx.clone(),format!("{} has folded.", player.name),).await;
-                        folded_count += 1;
-
-                        if folded_count == self.current_player_count - 1 {
-                            all_folded = true;
-                            // self.lobby_wide_send(players_tx.clone(), message.clone()).await;
-                            self.game_state = SHOWDOWN; // if only one player left they won, send to showdown to handle the pot distribution
-                            println!("All but one player folded, moving to showdown.");
-                        }
-                        players_remaining -= 1;
-                        break;
-                    }
-                    "5" => {
-                        // all in
-                        // side pots not considered yet
-                        if player.wallet > 0 {
-                            self.pot += player.wallet;
-                            player.current_bet += player.wallet;
-                            player.wallet -= player.wallet;
-                            if player.current_bet > current_lobby_bet {
-                                current_lobby_bet = player.current_bet;
-                                // reset the betting cycle if it pot was raised
-                                players_remaining = self.current_player_count - 1;
-                            } else {
-                                players_remaining -= 1;
+                        };
+                        match raise_to {
+                            Ok(raise_to) if betting.table_bet() == 0 => Action::Bet(raise_to),
+                            Ok(raise_to) => Action::RaiseTo(raise_to),
+                            Err(input) => {
+                                let reason = if input == "Timeout" { "ran out of time" } else { "has disconnected" };
+                                self.lobby_wide_send(players_tx.clone(), format!("{} {}.", player.name, reason)).await;
+                                if input == "Timeout" { timeout_action } else { Action::Fold }
                             }
-                            player.state = ALL_IN;
-                            // self.broadcast(format!("{} has gone all in!", player.name)).await;
-                            self.lobby_wide_send(players_tx.clone(),format!("{} has gone all in!", player.name),).await;
-                            break;
                         }
                     }
+                    "3" | "call" => Action::Call,
+                    "4" | "fold" => Action::Fold,
+                    "5" | "allin" => Action::AllIn,
                     "Disconnect" => {
-                        // self.broadcast(format!("{} has disconnected and folded.", player.name)).await;
                         self.lobby_wide_send(players_tx.clone(),format!("{} has disconnected and folded.", player.name)).await;
-                        player.state = FOLDED;
                         // Handle disconnection properly
                         drop(player.clone().rx);
-                        break;
+                        Action::Fold
+                    }
+                    "Timeout" => {
+                        self.lobby_wide_send(players_tx.clone(), format!("{} ran out of time.", player.name)).await;
+                        timeout_action
                     }
                     _ => {
                         player.tx.send(Message::text("Invalid action, try again.")).ok();
+                        continue;
+                    }
+                };
+                match betting.apply(seat, action) {
+                    Ok(events) => break events,
+                    Err(message) => {
+                        player.tx.send(Message::text(message)).ok();
                     }
                 }
-            }
+            };
 
-            if all_folded == true {
-                self.game_state = SHOWDOWN;
-                break;
+            // move the chips the engine moved
+            let added = betting.seat(seat).bet - player.current_bet;
+            player.wallet -= added;
+            player.current_bet += added;
+            player.total_bet += added;
+            self.pot += added;
+            let all_in = betting.seat(seat).all_in;
+            for event in events {
+                let (state, action, amount) = match event {
+                    Event::Folded { .. } => (FOLDED, ActionKind::Fold, None),
+                    Event::Checked { .. } => (CHECKED, ActionKind::Check, None),
+                    Event::Called { amount, .. } => (if all_in { ALL_IN } else { CALLED }, ActionKind::Call, Some(amount)),
+                    Event::Bet { to, .. } => (if all_in { ALL_IN } else { RAISED }, ActionKind::Bet, Some(to)),
+                    Event::RaisedTo { to, .. } => (if all_in { ALL_IN } else { RAISED }, ActionKind::Raise, Some(to)),
+                    Event::AllIn { to, .. } => (ALL_IN, ActionKind::AllIn, Some(to)),
+                    Event::HandWon { .. } => {
+                        // if only one player left they won, send to showdown to handle the pot distribution
+                        self.game_state = SHOWDOWN;
+                        println!("All but one player folded, moving to showdown.");
+                        continue;
+                    }
+                    Event::RoundComplete => continue,
+                };
+                player.state = state;
+                let recorded = match action {
+                    ActionKind::AllIn if amount.unwrap_or(0) <= table_bet => ActionKind::Call,
+                    ActionKind::AllIn if table_bet == 0 => ActionKind::Bet,
+                    ActionKind::AllIn => ActionKind::Raise,
+                    action => action,
+                };
+                self.actions.push(RecordedAction {
+                    player_id: player.id.clone(),
+                    betting_round: round as i32,
+                    action: recorded,
+                    amount,
+                });
+                self.lobby_wide_event(players_tx.clone(), protocol::Event::PlayerActed { player: player.name.clone(), action, amount }).await;
+            }
+            if added > 0 {
+                self.lobby_wide_event(players_tx.clone(), protocol::Event::PotUpdated { pot: self.pot }).await;
             }
-            // Move to next player
-            current_player_index = (current_player_index + 1) % self.current_player_count;
-            // players_remaining -= 1; // ensure we give everyone a change to do an action
         }
-        // if all but one player folded, the remaining player wins the pot
+        if betting.players_in_hand() <= 1 {
+            self.game_state = SHOWDOWN;
+        }
+        for player in players.iter_mut() {
+            player.current_bet = 0; // reset all players to 0 for the next betting round
+        }
     }
 
     async fn drawing_round(&mut self) {
@@ -618,37 +767,53 @@ impl Lobby {
             }
         }
         loop {
-            let player = &mut players[current_player_index as usize];
-            if player.state == FOLDED {
+            if players[current_player_index as usize].state == FOLDED {
                 current_player_index = (current_player_index + 1) % self.current_player_count;
                 continue
             };
             if count == player_count {break};
+            self.publish_table_state(&players, Some(&players[current_player_index as usize].name));
+            let player = &mut players[current_player_index as usize];
             println!("Drawing round for: {}", player.name);
 
             player.tx.send(Message::text("Drawing round!")).ok();
+            let mut turn = Turn::start(self.action_clock);
             loop {
-                let message = format!(
-                    "Choose an option:\n    1 - Stand Pat (Keep your hand)\n    2 - Exchange cards"
-                );
+                let message = "Choose an option:\n    1 - Stand Pat (Keep your hand)\n    2 - Exchange cards";
                 let _ = player.tx.send(Message::text(message));
     
-                let input = player.get_player_input().await;
+                let input = player.get_turn_input(&mut turn).await;
                 println!("Player input for drawing round: {}", input);
+                // an exchange may come with its cards, as in "2 1,3" or "draw 1,3"
+                let (command, mut argument) = match input.trim().split_once(' ') {
+                    Some((command, argument)) => (command, Some(argument.trim().to_string())),
+                    None => (input.trim(), None),
+                };
     
-                match input.as_str() {
-                    "1" => {
+                match command {
+                    "1" | "stand" => {
                         let _ = player.tx.send(Message::text("You chose to Stand Pat."));
+                        self.lobby_wide_event(players_tx.clone(), protocol::Event::PlayerActed { player: player.name.clone(), action: ActionKind::StandPat, amount: None }).await;
                         break;
                     }
-                    "2" => {
-                        let _ = player.tx.send(Message::text("Enter the indices of the cards you want to exchange (comma-separated, e.g., '1,2,3')"));
+                    "2" | "draw" => {
+                        if argument.is_none() {
+                            let _ = player.tx.send(Message::text("Enter the indices of the cards you want to exchange (comma-separated, e.g., '1,2,3')"));
+                        }
     
                         loop {
-                            let input = player.get_player_input().await;
+                            let input = match argument.take() {
+                                Some(argument) => argument,
+                                None => player.get_turn_input(&mut turn).await,
+                            };
+                            if input == "Timeout" || input == "Disconnect" {
+                                // keep the hand as it is
+                                self.lobby_wide_event(players_tx.clone(), protocol::Event::PlayerActed { player: player.name.clone(), action: ActionKind::StandPat, amount: None }).await;
+                                break;
+                            }
     
                             if let Some(indices_str) = input.strip_prefix("") {
-                                if !indices_str.chars().all(|c| c.is_digit(10) || c == ',' || c.is_whitespace()) {
+                                if !indices_str.chars().all(|c| c.is_ascii_digit() || c == ',' || c.is_whitespace()) {
                                     let _ = player.tx.send(Message::text("Invalid format. Use numbers separated by commas (e.g., '1,2,3')."));
                                     continue;
                                 }
@@ -672,16 +837,29 @@ impl Lobby {
     
                                 if valid_indices.len() == indices.len() && !valid_indices.is_empty() {
                                     let mut new_hand = Vec::new();
+                                    let mut discarded = Vec::new();
                                     for (i, card) in player.hand.iter().enumerate() {
                                         if !valid_indices.contains(&i) {
                                             new_hand.push(*card);
                                         }
                                     }
-                                    for _ in &valid_indices {
-                                        new_hand.push(self.deck.deal());
+                                    for &i in &valid_indices {
+                                        // if the deck and the discards are both used up, the player keeps the card
+                                        match self.deck.deal() {
+                                            Some(card) => {
+                                                new_hand.push(card);
+                                                discarded.push(player.hand[i]);
+                                            }
+                                            None => new_hand.push(player.hand[i]),
+                                        }
+                                    }
+                                    // discard only after dealing so a player never draws back their own cards
+                                    self.deck.discard(&discarded);
+                                    if discarded.len() < valid_indices.len() {
+                                        let _ = player.tx.send(Message::text(format!("The deck ran out of cards. You keep {} of the cards you wanted to exchange.", valid_indices.len() - discarded.len())));
                                     }
                                     player.hand = new_hand;
-                                    self.lobby_wide_send(players_tx.clone(), format!("{} has exchanged {} cards.", player.name, valid_indices.len())).await;
+                                    self.lobby_wide_event(players_tx.clone(), protocol::Event::PlayerActed { player: player.name.clone(), action: ActionKind::Draw, amount: Some(discarded.len() as i32) }).await;
     
                                     // Display the new hand to the player
                                     let updated_hand = vec![player.hand.clone()];
@@ -699,6 +877,11 @@ impl Lobby {
                         self.lobby_wide_send(players_tx.clone(), format!("{} has disconnected.", player.name)).await;
                         break;
                     }
+                    "Timeout" => {
+                        self.lobby_wide_send(players_tx.clone(), format!("{} ran out of time.", player.name)).await;
+                        self.lobby_wide_event(players_tx.clone(), protocol::Event::PlayerActed { player: player.name.clone(), action: ActionKind::StandPat, amount: None }).await;
+                        break;
+                    }
                     _ => {
                         let _ = player.tx.send(Message::text("Invalid choice. Please enter 1 or 2."));
                     }
@@ -711,46 +894,46 @@ impl Lobby {
 
     async fn showdown(&self) {
         let mut players = self.players.lock().await;
-        // let players: Vec<Player> = self.players.lock().await.to_vec();
         let players_tx = players.iter().map(|p| p.tx.clone()).collect::<Vec<_>>();
-        let mut winning_players: Vec<Player> = Vec::new(); // keeps track of winning players at the end, accounting for draws
-        let mut winning_players_names: Vec<String> = Vec::new();
-        let mut winning_hand = (0, 0, 0, 0, 0, 0); // keeps track of current highest hand, could change when incrementing between players
-        let mut winning_players_indices: Vec<i32> = Vec::new();
-        for player in players.iter_mut() {
-            if player.state == FOLDED {
+        let contributions: Vec<Contribution> = players
+            .iter()
+            .map(|p| Contribution { name: p.name.clone(), amount: p.total_bet, folded: p.state == FOLDED })
+            .collect();
+        let pots = build_pots(&contributions);
+        let names: Vec<String> = players.iter().map(|p| p.name.clone()).collect();
+        // every kicker is compared, so only truly identical hands split a pot
+        let awards = award_pots(&pots, &names, |i| self.variant.evaluate(&players[i].hand, &self.community_cards));
+
+        let mut pot_winners: Vec<bool> = vec![false; players.len()];
+        let mut results: Vec<PotResult> = Vec::new();
+        for (pot_index, (pot, award)) in pots.iter().zip(awards).enumerate() {
+            if award.winners.is_empty() {
                 continue;
-            };
-            let player_hand = player.hand.clone();
-            let player_hand_type = get_hand_type(&player_hand);
-            if player_hand_type.0 > winning_hand.0
-                || (player_hand_type.0 == winning_hand.0 && player_hand_type.1 > winning_hand.1)
-            {
-                winning_hand = player_hand_type;
-                winning_players.clear();
-                winning_players_names.clear();
-                winning_players.push(player.clone());
-                winning_players_names.push(player.name.clone());
-                winning_players_indices.clear();
-            } else if player_hand_type.0 == winning_hand.0 && player_hand_type.1 == winning_hand.1 {
-                winning_players.push(player.clone());
-                winning_players_names.push(player.name.clone());
             }
+            let pot_name = if pot_index == 0 { "Main pot".to_string() } else { format!("Side pot {}", pot_index) };
+            for (&i, share) in award.winners.iter().zip(split_pot(pot.amount, award.winners.len())) {
+                let player = &mut players[i];
+                player.wallet += share;
+                pot_winners[i] = true;
+                println!("Player {} wins {} from the {}!", player.name, share, pot_name.to_lowercase());
+                println!("Player {} wallet: {}", player.name, player.wallet);
+            }
+            results.push(PotResult {
+                name: pot_name,
+                amount: pot.amount,
+                eligible: pot.eligible.clone(),
+                winners: award.winners.iter().map(|&i| players[i].name.clone()).collect(),
+                hand: award.hand.map(|hand| hand.to_string()),
+            });
         }
-        let winning_player_count = winning_players.len();
-        let pot_share = self.pot / winning_player_count as i32;
-        for i in 0..winning_player_count {
-            for j in 0..players.len() {
-                if players[j].name == winning_players[i].name {
-                    players[j].games_won += 1;
-                    players[j].wallet += pot_share;
-                    println!("Player {} wins {}!", players[j].name, pot_share);
-                    println!("Player {} wallet: {}", players[j].name, players[j].wallet);
-                }
+        for (player, won) in players.iter_mut().zip(pot_winners) {
+            if won {
+                player.games_won += 1;
             }
         }
-        let winner_names = winning_players_names.join(", ");
-        self.lobby_wide_send(players_tx, format!("Winner: {}", winner_names)).await;
+        if !results.is_empty() {
+            self.lobby_wide_event(players_tx, protocol::Event::ShowdownResult { pots: results }).await;
+        }
     }
 
     async fn change_player_state(&self, state: i32) {
@@ -760,79 +943,32 @@ impl Lobby {
             println!("Changing {} state to: {}", player.name, state);
             player.state = state;
             player.hand.clear();
+            player.current_bet = 0;
+            player.total_bet = 0;
+            player.time_bank_used = false;
         }
     }
 
-    async fn translate_card(&self, card: i32) -> String {
-        let mut cardStr: String = Default::default();
-        let rank: i32 = card % 13;
-
-        if rank == 0 {
-            cardStr.push_str("Ace");
-        } else if rank <= 9 {
-            cardStr.push_str(&(rank + 1).to_string());
-        } else if rank == 10 {
-            cardStr.push_str("Jack");
-        } else if rank == 11 {
-            cardStr.push_str("Queen");
-        } else if rank == 12 {
-            cardStr.push_str("King");
-        }
-
-        let suit: i32 = card / 13;
-        if suit == 0 {
-            cardStr.push_str(" Hearts");
-        } else if suit == 1 {
-            cardStr.push_str(" Diamond");
-        } else if suit == 2 {
-            cardStr.push_str(" Spade");
-        } else if suit == 3 {
-            cardStr.push_str(" Club");
-        }
-        return cardStr;
-    }
-
-    async fn display_hand(&self, players_tx: Vec<UnboundedSender<Message>>, players_hands: Vec<Vec<i32>>) {
-        // let players = self.players;
-        let mut message: String;
-        let mut index = 0;
-        let mut count = 1;
-        for tx in players_tx.iter().cloned() {
-            let mut translated_cards: String = Default::default();
-            for card in players_hands[index].iter().cloned() {
-                // create a string like "count. "
-                translated_cards.push_str(&format!("{}. ", count));
-                translated_cards.push_str(&self.translate_card(card.clone()).await);
-                translated_cards.push_str("\n");
-                count += 1;
-            }
-            count = 1;
-            message = format!("Your hand:\n{}", translated_cards.trim_end_matches(", "));
-            let _ = tx.send(Message::text(message.clone()));
-            index += 1;
+    async fn display_hand(&self, players_tx: Vec<Outbox>, players_hands: Vec<Vec<Card>>) {
+        for (tx, cards) in players_tx.iter().zip(players_hands) {
+            let _ = tx.send_event(&protocol::Event::HandDealt { cards });
         }
     }
 
     
     async fn update_db(&self) {
-        // update the database with the new player stats
-        let players = self.players.lock().await;
-        for player in players.iter() {
-            println!("Updating player: {}", player.name);
-            println!("games played: {}", player.games_played);
-            println!("games won: {}", player.games_won);
-            println!("wallet: {}", player.wallet);
-            sqlx::query(
-                "UPDATE players SET games_played = games_played + ?1, games_won = games_won + ?2, wallet = ?3 WHERE name = ?4",
-            )
-            .bind(player.games_played)
-            .bind(player.games_won)
-            .bind(player.wallet)
-            .bind(&player.name)
-            .execute(&self.game_db)
-            .await
-            .unwrap();
-            // self.game_db.update_player_stats(&player).await.unwrap();
+        // update the database with the new player stats, then start counting again so no game is added twice
+        let db = Database::new(self.game_db.clone());
+        let mut players = self.players.lock().await;
+        for player in players.iter_mut() {
+            match db.update_player_stats(player).await {
+                Ok(()) => {
+                    player.games_played = 0;
+                    player.games_won = 0;
+                }
+                // the counts are kept, so they are added with the next update instead
+                Err(e) => eprintln!("Failed to update the stats of {}: {}", player.name, e),
+            }
         }
     }
 
@@ -843,182 +979,199 @@ impl Lobby {
         self.game_state = START_OF_ROUND;
         self.change_player_state(IN_GAME).await;
 
+        // remember where everyone started so each player's result can be recorded once the game is over
+        let starting = self.players.lock().await.iter()
+            .map(|player| (player.id.clone(), player.wallet, player.games_won))
+            .collect::<Vec<_>>();
+        self.actions.clear();
+        let db = Database::new(self.game_db.clone());
+        let game_number = match db.start_game(self.variant.name(), &self.name).await {
+            Ok(game_number) => {
+                self.broadcast(format!("Game #{} is starting.", game_number)).await;
+                Some(game_number)
+            }
+            Err(e) => {
+                eprintln!("Failed to record the start of a game in {}: {}", self.name, e);
+                None
+            }
+        };
+
         self.game_state_machine().await;
 
+        if let Some(game_number) = game_number {
+            // players who left during the game are not in the lobby anymore, so their result is not known
+            let players = self.players.lock().await;
+            let pot = players.iter().map(|player| player.total_bet).sum::<i32>();
+            // the hand was only shown down if more than one player was left in it
+            let showdown = players.iter().filter(|player| player.state != FOLDED).count() > 1;
+            let results = players.iter()
+                .filter_map(|player| {
+                    let (_, wallet, games_won) = starting.iter().find(|(id, _, _)| *id == player.id)?;
+                    Some(GameResult {
+                        player_id: player.id.clone(),
+                        net_result: player.wallet - wallet,
+                        final_hand: (player.state != FOLDED)
+                            .then(|| player.hand.iter().map(|card| card.to_string()).collect::<Vec<_>>().join(" ")),
+                        won: player.games_won > *games_won,
+                        showdown: showdown && player.state != FOLDED,
+                        // every chip in the wallet that was not there at the start, or put into the pot, came from a pot
+                        collected: player.wallet - wallet + player.total_bet,
+                    })
+                })
+                .collect::<Vec<_>>();
+            drop(players);
+            if let Err(e) = db.finish_game(game_number, pot, &results, &self.actions).await {
+                eprintln!("Failed to record the results of game #{}: {}", game_number, e);
+            }
+        }
+        // after the results, which tell who won from the win counts
+        self.update_db().await;
+
         self.game_state = JOINABLE;
         self.change_player_state(IN_LOBBY).await;
-        self.broadcast(format!("Welcome to lobby: {}\nChoose an option:\n1. Ready:           r\n2. Show Players:    p\n3. View stats:      s\n4. Quit:            q\n\n", self.name)).await;
-        return;
+        self.broadcast(format!("Welcome to lobby: {}\nChoose an option:\n1. Ready:           r\n2. Show Players:    p\n3. View stats:      s [player ID or name]\n4. Quit:            q\n\n", self.name)).await;
     }
 
     async fn game_state_machine(&mut self) {
-        loop {
-            match self.game_state {
-                START_OF_ROUND => {
-                    self.first_betting_player = (self.first_betting_player + 1) % self.current_player_count;
-                    self.game_state = ANTE;
-                }
-                ANTE => {
-                    self.broadcast("Ante round!\nEveryone adds $10 to the pot.".to_string()).await;
-                    self.betting_round(ANTE).await;
-                    self.broadcast(format!("Current pot: {}", self.pot)).await;
-                    self.game_state = DEAL_CARDS;
+        const ORDINALS: [&str; 4] = ["First", "Second", "Third", "Fourth"];
+        let mut betting_round_count = 0;
+        let betting_rounds = self.variant.phases().iter().filter(|&&phase| phase == Phase::Bet).count();
+        let mut draw_count = 0;
+
+        for phase in self.variant.phases() {
+            // once all but one player has folded, skip straight to the showdown
+            if self.game_state == SHOWDOWN && phase != Phase::Showdown {
+                continue;
+            }
+            match phase {
+                Phase::ForcedBets => {
+                    self.post_forced_bets().await;
+                    self.broadcast_event(protocol::Event::PotUpdated { pot: self.pot }).await;
                 }
-                DEAL_CARDS => {
+                Phase::DealHoleCards => {
                     self.broadcast("Dealing cards...".to_string()).await;
                     self.deck.shuffle(); // shuffle card deck
+                    // publish a commitment to the shuffle seed so players can verify the deal afterwards
+                    self.broadcast(format!("Shuffle commitment (SHA-256 of the seed): {}", self.deck.commitment())).await;
                     self.deal_cards().await; // deal and display each players hands to them
-                    self.game_state = FIRST_BETTING_ROUND;
                 }
-                FIRST_BETTING_ROUND => {
-                    self.broadcast("------First betting round!------".to_string()).await;
-                    self.betting_round(FIRST_BETTING_ROUND).await;
-                    if self.game_state == SHOWDOWN {
-                        continue;
-                    }else {
-                        self.game_state = DRAW;
-                        self.broadcast(format!("First betting round complete!\nCurrent pot: {}", self.pot)).await;
-                    }
+                Phase::DealCommunityCards { name, count } => {
+                    self.broadcast(format!("------Dealing the {}!------", name)).await;
+                    self.deal_community_cards(count).await;
                 }
-                DRAW => {
-                    self.broadcast("------Drawing round!------".to_string()).await;
-                    self.drawing_round().await;
-                    // self.display_hand().await;
-                    self.game_state = SECOND_BETTING_ROUND;
+                Phase::Bet => {
+                    let ordinal = ORDINALS.get(betting_round_count).copied().unwrap_or("Next");
+                    self.broadcast(format!("------{} betting round!------", ordinal)).await;
+                    self.betting_round(betting_round_count, betting_rounds).await;
+                    betting_round_count += 1;
+                    // after the opening round, the first player left of the button always acts first
+                    self.first_betting_player = (self.button + 1).rem_euclid(self.current_player_count);
+                    self.broadcast(format!("{} betting round complete!\nCurrent pot: {}", ordinal, self.pot)).await;
                 }
-                SECOND_BETTING_ROUND => {
-                    self.broadcast("Second betting round!".to_string()).await;
-                    self.betting_round(FIRST_BETTING_ROUND).await;
-                    self.broadcast(format!("Second betting round complete!\nCurrent pot: {}", self.pot)).await;
-                    self.game_state = SHOWDOWN;
+                Phase::Draw => {
+                    draw_count += 1;
+                    if draw_count == 1 {
+                        self.broadcast("------Drawing round!------".to_string()).await;
+                    } else {
+                        let ordinal = ORDINALS.get(draw_count - 1).copied().unwrap_or("Next");
+                        self.broadcast(format!("------{} drawing round!------", ordinal)).await;
+                    }
+                    self.drawing_round().await;
                 }
-                SHOWDOWN => {
+                Phase::Showdown => {
                     self.broadcast("------Showdown Round!------".to_string()).await;
                     self.showdown().await;
-                    self.game_state = END_OF_ROUND;
-                }
-                END_OF_ROUND => {
-                    self.game_state = UPDATE_DB;
-                }
-                UPDATE_DB => {
-                    self.pot = 0;
-                    self.update_db().await;
-                    break;
-               }
-                _ => {
-                    panic!("Invalid game state: {}", self.game_state);
                 }
             }
         }
-    }
-}
-
-fn get_hand_type(hand: &[i32]) -> (i32, i32, i32, i32, i32, i32) {
-    assert!(hand.len() == 5);
-
-    let mut ranks: Vec<i32> = hand
-        .iter()
-        .map(|&card| if card % 13 != 0 { card % 13 } else { 13 })
-        .collect();
-    ranks.sort();
-
-    let suits: Vec<i32> = hand.iter().map(|&card| card / 13).collect();
-
-    // Check for flush
-    let flush = suits.iter().all(|&suit| suit == suits[0]);
-
-    // Check for straight
-    let straight = ranks.windows(2).all(|w| w[1] == w[0] + 1);
-
-    if flush && straight {
-        return (8, ranks[4], ranks[4], 0, 0, 0);
-    }
-
-    // Check for four of a kind
-    for i in 0..2 {
-        if ranks[i] == ranks[i + 1] && ranks[i] == ranks[i + 2] && ranks[i] == ranks[i + 3] {
-            return if i == 0 {
-                (7, ranks[i], ranks[4], 0, 0, 0)
-            } else {
-                (7, ranks[i], ranks[0], 0, 0, 0)
-            };
-        }
-    }
 
-    // Check for full house
-    if ranks[0] == ranks[1] && ranks[3] == ranks[4] {
-        if ranks[2] == ranks[0] {
-            return (6, ranks[0], ranks[4], 0, 0, 0);
-        } else if ranks[2] == ranks[4] {
-            return (6, ranks[4], ranks[0], 0, 0, 0);
+        self.broadcast(format!(
+            "Shuffle seed for this hand: {}\nIts SHA-256 hash matches the commitment published before the deal.",
+            to_hex(&self.deck.seed())
+        )).await;
+        self.pot = 0;
+        self.community_cards.clear();
+        // the hand is over, so there is nothing to catch a reconnecting player up on
+        for player in self.players.lock().await.iter() {
+            player.session.set_table_state(None);
         }
     }
+}
 
-    if flush {
-        return (5, ranks[4], ranks[3], ranks[2], ranks[1], ranks[0]);
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{detached, Protocol};
+    use tokio::sync::mpsc;
 
-    if straight {
-        return (4, ranks[4], 0, 0, 0, 0);
+    async fn setup_database() -> SqlitePool {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::raw_sql(include_str!("../schema.sql")).execute(&pool).await.unwrap();
+        pool
     }
 
-    // Check 3 of a kind
-    for i in 0..3 {
-        if ranks[i] == ranks[i + 1] && ranks[i] == ranks[i + 2] {
-            return match i {
-                0 => (3, ranks[i], ranks[4], ranks[3], 0, 0),
-                1 => (3, ranks[i], ranks[4], ranks[0], 0, 0),
-                2 => (3, ranks[i], ranks[1], ranks[0], 0, 0),
-                _ => unreachable!(),
-            };
+    /// A player who is not connected to a socket, seated in `lobby`.
+    fn player(name: &str, id: &str, wallet: i32, lobby: Arc<Mutex<Lobby>>) -> Player {
+        let (inbox, session, _) = detached(Duration::ZERO);
+        let (tx, _) = mpsc::unbounded_channel();
+        Player {
+            name: name.to_string(),
+            id: id.to_string(),
+            hand: Vec::new(),
+            wallet,
+            tx: Outbox::new(tx, Protocol::Text),
+            rx: Arc::new(Mutex::new(inbox)),
+            session,
+            state: IN_SERVER,
+            current_bet: 0,
+            total_bet: 0,
+            dealer: false,
+            ready: false,
+            time_bank_used: false,
+            games_played: 0,
+            games_won: 0,
+            lobby,
         }
     }
 
-    // Check two pair
-    if ranks[0] == ranks[1] && ranks[2] == ranks[3] {
-        return (
-            3,
-            ranks[0].max(ranks[2]),
-            ranks[0].min(ranks[2]),
-            ranks[4],
-            0,
-            0,
-        );
-    } else if ranks[0] == ranks[1] && ranks[3] == ranks[4] {
-        return (
-            3,
-            ranks[0].max(ranks[3]),
-            ranks[0].min(ranks[3]),
-            ranks[2],
-            0,
-            0,
-        );
-    } else if ranks[1] == ranks[2] && ranks[3] == ranks[4] {
-        return (
-            3,
-            ranks[1].max(ranks[3]),
-            ranks[1].min(ranks[3]),
-            ranks[0],
-            0,
-            0,
-        );
+    #[tokio::test]
+    async fn test_join_after_reset_uses_the_reset_wallet() {
+        let pool = setup_database().await;
+        let db = Database::new(pool.clone());
+        let id = db.register_player("alice", "pw").await.unwrap();
+        sqlx::query("UPDATE players SET wallet = 400 WHERE name = 'alice'").execute(&pool).await.unwrap();
+
+        let server_lobby = Arc::new(Mutex::new(Lobby::with_db(None, "Server Lobby".to_string(), None, pool.clone())));
+        let table = Arc::new(Mutex::new(Lobby::with_db(None, "table".to_string(), None, pool.clone())));
+        server_lobby.lock().await.add_lobby(table.clone()).await;
+        // the server menu's copy of alice still holds the wallet she logged in with
+        let mut alice = player("alice", &id, 400, server_lobby.clone());
+
+        assert_eq!(server_lobby.lock().await.reset_history().await, SUCCESS);
+        assert_eq!(alice.player_join_lobby(server_lobby.clone(), "table".to_string()).await, SUCCESS);
+        assert_eq!(alice.wallet, STARTING_WALLET);
+        assert_eq!(table.lock().await.players.lock().await[0].wallet, STARTING_WALLET);
     }
 
-    // Check one pair
-    for i in 0..4 {
-        if ranks[i] == ranks[i + 1] {
-            return match i {
-                0 => (2, ranks[i], ranks[4], ranks[3], ranks[2], 0),
-                1 => (2, ranks[i], ranks[4], ranks[3], ranks[0], 0),
-                2 => (2, ranks[i], ranks[4], ranks[1], ranks[0], 0),
-                3 => (2, ranks[i], ranks[2], ranks[1], ranks[0], 0),
-                _ => unreachable!(),
-            };
+    #[tokio::test]
+    async fn test_each_game_is_counted_once() {
+        let pool = setup_database().await;
+        let db = Database::new(pool.clone());
+        let id = db.register_player("alice", "pw").await.unwrap();
+        let table = Arc::new(Mutex::new(Lobby::with_db(None, "table".to_string(), None, pool.clone())));
+        let alice = player("alice", &id, STARTING_WALLET, table.clone());
+        table.lock().await.add_player(alice).await;
+
+        for won in [true, false, true] {
+            let table = table.lock().await;
+            {
+                let mut players = table.players.lock().await;
+                players[0].games_played += 1;
+                players[0].games_won += won as i32;
+            }
+            table.update_db().await;
         }
+        let stats = db.player_stats(&id).await.unwrap();
+        assert_eq!((stats.games_played, stats.games_won), (3, 2));
     }
-
-    // High card
-    (1, ranks[4], ranks[3], ranks[2], ranks[1], ranks[0])
 }
-
-
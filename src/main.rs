@@ -27,7 +27,7 @@
 //! cargo run
 //! ```
 //! 
-//! The server will start on `localhost:1112` and listen for incoming WebSocket connections.
+//! The server will start on `localhost:1112` and listen for incoming WebSocket connections. The same port serves a read-only JSON API over plain HTTP, described in the `api` module.
 //! 
 //! Clients can connect to the server using a WebSocket client, such as `websocat` or a web browser.
 //! 
@@ -38,25 +38,40 @@
 //! - `sqlx` for the SQLite database interaction
 //! - `uuid` for generating unique player IDs
 //! - `tokio` for the asynchronous runtime
+//! - `rand_chacha` and `sha2` for the seeded, verifiable deck shuffle
 //! 
 //! # Modules
 //! 
 //! The server is organized into the following modules:
+//! - `api` - API module for the read-only HTTP endpoints serving stats, games, lobbies and the leaderboard as JSON
+//! - `betting` - Betting module for the forced bets of a lobby and the position of the dealer button
+//! - `card` - Card module for the typed `Card`, `Rank` and `Suit` representation, shared with clients through the `poker-protocol` crate
+//! - `clock` - Clock module for the time limit on each player's turn
 //! - `database` - Database module for player registration, login, and statistics
 //! - `deck` - Deck module for managing the deck of cards
+//! - `evaluator` - Evaluator module for scoring and comparing poker hands
 //! - `lobby` - Lobby module for managing players and lobbies
+//! - `pot` - Pot module for splitting the chips into a main pot and side pots
+//! - `protocol` - Protocol module for the text and versioned JSON message formats of a connection
+//! - `variant` - Variant module describing the flow and hand evaluation of each poker game
+mod api;
+mod betting;
+mod clock;
 mod database;
 mod deck;
+mod evaluator;
 mod lobby;
+mod pot;
+mod protocol;
+mod variant;
 
-use futures_util::stream::SplitStream;
 use futures_util::{StreamExt, SinkExt};
-use warp::Filter;
+use warp::{Filter, Reply};
+use poker_protocol::card;
 use warp::ws::{Message, WebSocket};
 use std::sync::Arc;
 use database::Database;
 use sqlx::SqlitePool;
-use uuid::Uuid;
 use tokio::sync::{mpsc, Mutex};
 use tokio::time::{sleep, Duration};
 use lobby::*;
@@ -65,6 +80,93 @@ use deck::Deck;
 /// The maximum number of players allowed in the server.
 const MAX_SERVER_PLAYER_COUNT: i32 = 100;
 
+/// The environment variable that sets the `DuplicateLoginPolicy`, `reject` or `takeover`.
+const DUPLICATE_LOGIN_ENV: &str = "POKER_DUPLICATE_LOGIN";
+
+/// The server menu, shown when a player joins the server and on request with option 5.
+const SERVER_MENU: &str = "Choose an option:\n\
+Create new lobby with lobby name, game type (draw, deuces, joker, holdem or badugi) and optional settings in any order: stakes (ante, ante=N, blinds or blinds=S/B), betting limit (nolimit, potlimit, fixed or fixed=S/B) and action clock (clock=SECONDS, clock=SECONDS+BANK or clock=off)\n\
+\t1 [lobby name] [game type] [settings]\n\
+Join lobby with lobby name\n\
+\t2 [lobby name]\n\
+Show current lobbies\n\
+\t3\n\
+Show stats for yourself, or any player by ID or name\n\
+\t4 [player ID or name]\n\
+Show commands\n\
+\t5\n\
+Quit\n\
+\t6\n\
+Show your result in a game\n\
+\t7 [game number]\n\
+Reset game numbering and player stats (administrators only)\n\
+\t8\n\
+Show a leaderboard by wallet, wins, winrate[=MIN_GAMES] or profit[=DAYS]\n\
+\t9 [metric] [page]\n\
+Show VPIP, preflop raise rate, aggression and showdown stats per variant for yourself or any player\n\
+\t10 [player ID or name]\n\
+";
+
+/// The environment variable naming the accounts to make administrators at startup, separated by commas, such as `alice,bob`.
+///
+/// Only accounts that have already registered can be made administrators. Administrators stay so until `is_admin` is cleared in the database.
+const ADMINS_ENV: &str = "POKER_ADMINS";
+
+/// What happens when an account that is already logged in logs in again from another connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DuplicateLoginPolicy {
+    /// Turn the new login away.
+    Reject,
+    /// Move the existing session, with its seat and lobby, to the new connection and close the old one.
+    TakeOver,
+}
+
+impl std::str::FromStr for DuplicateLoginPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(DuplicateLoginPolicy::Reject),
+            "takeover" => Ok(DuplicateLoginPolicy::TakeOver),
+            _ => Err(format!("Invalid {} '{}'. Use reject or takeover.", DUPLICATE_LOGIN_ENV, s)),
+        }
+    }
+}
+
+/// Parses the optional settings typed after the game type when creating a lobby.
+///
+/// # Arguments
+/// * `variant` - The game the lobby will play, which decides the stakes if none are given.
+/// * `settings` - The stakes, betting limit and action clock, in any order.
+///
+/// # Returns
+/// * `Ok` - The lobby's forced bets, betting limit and action clock.
+/// * `Err(String)` - A message for the player if a setting is not recognised.
+fn parse_lobby_settings(
+    variant: &dyn variant::PokerVariant,
+    settings: &[&str],
+) -> Result<(betting::ForcedBets, betting::BetLimit, Option<clock::ActionClock>), String> {
+    // without stakes the lobby plays the variant's usual ante or blinds
+    let mut forced_bets = variant.forced_bets();
+    let mut bet_limit = None;
+    let mut action_clock = Some(clock::ActionClock::default());
+    for setting in settings {
+        if setting.starts_with("ante") || setting.starts_with("blinds") {
+            forced_bets = setting.parse()?;
+        } else if setting.starts_with("clock") {
+            action_clock = clock::parse_action_clock(setting)?;
+        } else {
+            bet_limit = Some(*setting);
+        }
+    }
+    // fixed-limit bet sizes default to the stakes, so the limit is parsed last
+    let bet_limit = match bet_limit {
+        Some(limit) => betting::BetLimit::parse(limit, forced_bets)?,
+        None => betting::BetLimit::NoLimit,
+    };
+    Ok((forced_bets, bet_limit, action_clock))
+}
+
 #[tokio::main]
 async fn main() {
     let db_pool = SqlitePool::connect("sqlite://poker.db").await.expect(
@@ -72,17 +174,51 @@ async fn main() {
     );
 
     let database = Arc::new(Database::new(db_pool.clone()));
-    let server_lobby = Arc::new(Mutex::new(Lobby::new(Some(MAX_SERVER_PLAYER_COUNT), "Server Lobby".to_string()).await));
+    // databases made by older versions of the server are missing newer tables and columns
+    database.migrate().await.expect("Failed to migrate database");
+    if let Ok(admins) = std::env::var(ADMINS_ENV) {
+        let admins = admins.split(',').map(str::trim).filter(|name| !name.is_empty()).collect::<Vec<_>>();
+        match database.grant_admins(&admins).await {
+            Ok(unknown) => {
+                for name in unknown {
+                    eprintln!("{} names {}, who has not registered, so they were not made an administrator.", ADMINS_ENV, name);
+                }
+            }
+            Err(e) => eprintln!("Failed to make the accounts in {} administrators: {}", ADMINS_ENV, e),
+        }
+    }
+    let duplicate_login_policy = match std::env::var(DUPLICATE_LOGIN_ENV).map(|policy| policy.parse()) {
+        Ok(Ok(policy)) => policy,
+        Ok(Err(e)) => {
+            eprintln!("{} Falling back to reject.", e);
+            DuplicateLoginPolicy::Reject
+        }
+        Err(_) => DuplicateLoginPolicy::Reject,
+    };
+    let server_lobby = Arc::new(Mutex::new(Lobby::new(Some(MAX_SERVER_PLAYER_COUNT), "Server Lobby".to_string(), None).await));
     let register_route = warp
         ::path("ws")
         .and(warp::ws())
         .and(with_db(database.clone()))
         .and(with_lobby(server_lobby.clone()))
-        .map(|ws: warp::ws::Ws, db, server_lobby|
-            ws.on_upgrade(move |socket| handle_connection(socket, db, server_lobby))
-        );
+        .and(warp::header::optional::<String>("sec-websocket-protocol"))
+        .map(move |ws: warp::ws::Ws, db, server_lobby, offered: Option<String>| {
+            // clients that ask for a JSON subprotocol get it, everyone else stays on plain text
+            let protocol = protocol::negotiate(offered.as_deref());
+            let reply = ws.on_upgrade(move |socket| handle_connection(socket, db, server_lobby, protocol, duplicate_login_policy));
+            match protocol.subprotocol() {
+                Some(subprotocol) => warp::reply::with_header(reply, "sec-websocket-protocol", subprotocol).into_response(),
+                None => reply.into_response(),
+            }
+        });
+    // the JSON Schema of every request and event, for third-party clients and test tools
+    let schema_route = warp::path!("protocol" / "schema.json")
+        .and(warp::get())
+        .map(|| warp::reply::json(&protocol::schema()));
 
-    warp::serve(register_route).run(([0, 0, 0, 0], 1112)).await;
+    let api_routes = api::routes(database.clone(), server_lobby.clone());
+
+    warp::serve(register_route.or(schema_route).or(api_routes)).run(([0, 0, 0, 0], 1112)).await;
 }
 
 fn with_db(
@@ -105,7 +241,7 @@ fn with_lobby(
     /// 
     /// # Returns
     /// 
-    /// A list of tuples where each tuple contains the name of a lobby and its status.
+    /// A list of tuples where each tuple contains the name of a lobby, its status and its variant.
 async fn get_lobby_names(server_lobby: Arc<Mutex<Lobby>>) -> String {
 
     let lobbies = server_lobby.lock().await.get_lobby_names_and_status().await;
@@ -113,17 +249,108 @@ async fn get_lobby_names(server_lobby: Arc<Mutex<Lobby>>) -> String {
     if lobbies.is_empty() {
         lobby_list = "No lobbies available.".to_string();
     } else {
-        for (lobby_name, lobby_status) in lobbies {
+        for (lobby_name, lobby_status, lobby_variant) in lobbies {
             if lobby_status == lobby::JOINABLE {
-                lobby_list.push_str(&format!("{}\t--{}--\n", lobby_name, "Joinable"));
+                lobby_list.push_str(&format!("{}\t[{}]\t--{}--\n", lobby_name, lobby_variant, "Joinable"));
             } else {
-                lobby_list.push_str(&format!("{}\t--{}--\n", lobby_name, "Not Joinable"));
+                lobby_list.push_str(&format!("{}\t[{}]\t--{}--\n", lobby_name, lobby_variant, "Not Joinable"));
             }
         }
     }
     lobby_list
 }
 
+/// Reads the next line of input before the player has logged in.
+/// 
+/// # Arguments
+/// 
+/// * `inbox` - The connection's incoming messages.
+/// 
+/// # Returns
+/// 
+/// The trimmed input, or `None` if the connection was closed.
+async fn read_line(inbox: &mut protocol::Inbox) -> Option<String> {
+    loop {
+        let msg = inbox.next_message().await?.ok()?;
+        if msg.is_close() {
+            return None;
+        }
+        if let Ok(text) = msg.to_str() {
+            return Some(text.trim().to_string());
+        }
+    }
+}
+
+/// Asks for a username and password.
+/// 
+/// # Arguments
+/// 
+/// * `tx` - The connection's outgoing messages.
+/// * `inbox` - The connection's incoming messages.
+/// * `prompt` - The prompt for the username.
+/// 
+/// # Returns
+/// 
+/// The username and password, or `None` if the connection was closed.
+async fn read_credentials(tx: &protocol::Outbox, inbox: &mut protocol::Inbox, prompt: &str) -> Option<(String, String)> {
+    tx.send(Message::text(prompt)).unwrap();
+    let username = read_line(inbox).await?;
+    tx.send(Message::text("Enter your password:")).unwrap();
+    let password = read_line(inbox).await?;
+    Some((username, password))
+}
+
+/// Sends a player's statistics, looked up by ID or username.
+/// 
+/// # Arguments
+/// 
+/// * `tx` - The connection's outgoing messages.
+/// * `db` - The database connection pool.
+/// * `player` - The ID or username of the player to look up.
+async fn send_player_stats(tx: &protocol::Outbox, db: &Database, player: &str) {
+    match db.player_stats(player).await {
+        Ok(stats) => {
+            let _ = tx.send_event(&protocol::Event::PlayerStats(stats.into()));
+        }
+        Err(sqlx::Error::RowNotFound) => {
+            let _ = tx.send(Message::text(format!("No player found with ID or name {}.", player)));
+        }
+        Err(_) => {
+            let _ = tx.send(Message::text("Failed to retrieve stats."));
+        }
+    }
+}
+
+/// Sends a player's VPIP, preflop raise rate, aggression and showdown stats in each variant, looked up by ID or username.
+/// 
+/// # Arguments
+/// 
+/// * `tx` - The connection's outgoing messages.
+/// * `db` - The database connection pool.
+/// * `player` - The ID or username of the player to look up.
+async fn send_variant_stats(tx: &protocol::Outbox, db: &Database, player: &str) {
+    let stats = match db.player_stats(player).await {
+        Ok(stats) => stats,
+        Err(sqlx::Error::RowNotFound) => {
+            let _ = tx.send(Message::text(format!("No player found with ID or name {}.", player)));
+            return;
+        }
+        Err(_) => {
+            let _ = tx.send(Message::text("Failed to retrieve stats."));
+            return;
+        }
+    };
+    match db.variant_stats(&stats.id).await {
+        Ok(variants) => {
+            let variants = variants.into_iter().map(Into::into).collect();
+            let _ = tx.send_event(&protocol::Event::VariantStats { player: stats.name, variants });
+        }
+        Err(_) => {
+            let _ = tx.send(Message::text("Failed to retrieve stats."));
+        }
+    }
+}
+
 /// Handles a new WebSocket connection.
 /// 
 /// This function is called for each new WebSocket connection and is responsible for
@@ -134,146 +361,192 @@ async fn get_lobby_names(server_lobby: Arc<Mutex<Lobby>>) -> String {
 /// * `ws` - The WebSocket connection.
 /// * `db` - The database connection pool.
 /// * `server_lobby` - The server lobby containing all players and lobbies.
+/// * `protocol` - The message format negotiated for the connection.
+/// * `duplicate_login_policy` - What to do if the account is already logged in on another connection.
 /// 
 /// # Returns
 /// 
 /// This function does not return a value, but it sends messages to the client
 /// via the WebSocket connection.
-async fn handle_connection(ws: WebSocket, db: Arc<Database>, server_lobby: Arc<Mutex<Lobby>>) {
-    let (mut ws_tx, mut ws_rx) = ws.split();
-    let (tx, mut rx) = mpsc::unbounded_channel();
-    let mut username_id = "".to_string();
-    let mut current_player: Player;
+async fn handle_connection(
+    ws: WebSocket,
+    db: Arc<Database>,
+    server_lobby: Arc<Mutex<Lobby>>,
+    protocol: protocol::Protocol,
+    duplicate_login_policy: DuplicateLoginPolicy,
+) {
+    let (mut ws_tx, ws_rx) = ws.split();
+    let (raw_tx, mut rx) = mpsc::unbounded_channel();
+    let tx = protocol::Outbox::new(raw_tx, protocol);
+    let (mut ws_rx, session) = protocol::listen(ws_rx, tx.clone());
 
     tokio::spawn(async move {
         while let Some(msg) = rx.recv().await {
             let _ = ws_tx.send(msg).await;
         }
+        // nothing is left to send once the session ends or moves to another connection
+        let _ = ws_tx.close().await;
     });
 
     tx.send(Message::text("Welcome to Poker!\n")).unwrap();
     sleep(Duration::from_secs(2)).await;
 
-    loop {
-        tx.send(Message::text("Choose an option:\n1. Login\n2. Register\n3. Quit")).unwrap();
-        if let Some(Ok(msg)) = ws_rx.next().await {
-            if let Ok(choice) = msg.to_str() {
-                match choice.trim() {
-                    "1" => {
-                        let prompt_msg = Message::text("Enter your username:");
-                        tx.send(prompt_msg).unwrap();
-
-                        if let Some(Ok(username_msg)) = ws_rx.next().await {
-                            if let Ok(username) = username_msg.to_str() {
-                                let username = username.trim().to_string();
-                                match db.login_player(&username).await {
-                                    Ok(Some(_id)) => {
-                                        tx.send(
-                                            Message::text(format!("Welcome back, {}!", username))
-                                        ).unwrap();
-
-                                        let new_player = Player {
-                                            name: username.clone(),
-                                            id: Uuid::new_v4().to_string(),
-                                            hand: Vec::new(),
-                                            wallet: db.get_player_wallet(&username).await.unwrap() as i32,
-                                            tx: tx.clone(),
-                                            rx: Arc::new(Mutex::new(ws_rx)),
-                                            state: lobby::IN_SERVER,
-                                            current_bet: 0,
-                                            dealer: false,
-                                            ready: false,
-                                            games_played: 0,
-                                            games_won: 0,
-                                            lobby: server_lobby.clone(),
-                                        };
-
-                                        server_lobby.lock().await.add_player(new_player.clone()).await;
-                                        server_lobby.lock().await.broadcast(format!("{} has joined the server!", username)).await;
-                                        
-                                        username_id = username;
-                                        println!("{} has joined the server!", username_id.clone());
-                                        println!("server player count: {}", server_lobby.lock().await.current_player_count);
-                                        println!("Server players:\n{}\n\n", server_lobby.lock().await.get_player_names().await);
-                                        current_player = new_player;
-                                        break;
-                                    }
-                                    _ => {
-                                        tx.send(
-                                            Message::text("Username not found. Try again.")
-                                        ).unwrap();
-                                    }
-                                }
-                            }
-                        }
+    // log in, register or resume a session until the player is authenticated
+    let (player_id, username, resumed) = loop {
+        tx.send(Message::text("Choose an option:\n1. Login\n2. Register\n3. Quit\n4. Resume session")).unwrap();
+        let Some(choice) = read_line(&mut ws_rx).await else {
+            return;
+        };
+        match choice.as_str() {
+            "1" | "login" => {
+                let Some((username, password)) = read_credentials(&tx, &mut ws_rx, "Enter your username:").await else {
+                    return;
+                };
+                match db.login_player(&username, &password).await {
+                    Ok(Some(id)) => {
+                        tx.send(Message::text(format!("Welcome back, {}!", username))).unwrap();
+                        break (id, username, false);
                     }
-                    "2" => {
-                        let prompt_msg = Message::text("Enter a new username to register:");
-                        tx.send(prompt_msg).unwrap();
-
-                        if let Some(Ok(username_msg)) = ws_rx.next().await {
-                            if let Ok(username) = username_msg.to_str() {
-                                let username = username.trim().to_string();
-                                match db.register_player(&username).await {
-                                    Ok(_) => {
-                                        tx.send(
-                                            Message::text(
-                                                format!("Registration successful! Welcome, {}! You are now in the Server.", username)
-                                            )
-                                        ).unwrap();
-                                        let new_player = Player {
-                                            name: username.clone(),
-                                            id: Uuid::new_v4().to_string(),
-                                            hand: Vec::new(),
-                                            wallet: 1000,
-                                            tx: tx.clone(),
-                                            rx: Arc::new(Mutex::new(ws_rx)),
-                                            state: lobby::IN_SERVER,
-                                            current_bet: 0,
-                                            dealer: false,
-                                            ready: false,
-                                            games_played: 0,
-                                            games_won: 0,
-                                            lobby: server_lobby.clone(),
-                                        };
-
-                                        server_lobby.lock().await.add_player(new_player.clone()).await;
-                                        server_lobby.lock().await.broadcast(
-                                            format!("{} has joined the server!", username)
-                                        ).await;
-                                        username_id = username;
-                                        println!("{} has joined the server!", username_id.clone());
-                                        println!("server player count: {}", server_lobby.lock().await.current_player_count);
-                                        current_player = new_player;
-                                        break;
-                                    }
-                                    Err(_) => {
-                                        tx.send(
-                                            Message::text("Registration failed. Try again.")
-                                        ).unwrap();
-                                    }
-                                }
-                            }
+                    Ok(None) if !password.is_empty() => {
+                        // accounts made before passwords were added take the first password they log in with
+                        if let Ok(Some(id)) = db.claim_account(&username, &password).await {
+                            tx.send(Message::text(format!(
+                                "Welcome back, {}! Your account had no password, so the one you entered is now its password.",
+                                username
+                            )))
+                            .unwrap();
+                            break (id, username, false);
                         }
+                        tx.send(Message::text("Incorrect username or password. Try again.")).unwrap();
                     }
-                    "3" => {
-                        tx.send(Message::text("Goodbye!")).unwrap();
-                        server_lobby.lock().await.remove_player(username_id.clone()).await;
-                        return;
+                    _ => {
+                        tx.send(Message::text("Incorrect username or password. Try again.")).unwrap();
+                    }
+                }
+            }
+            "2" | "register" => {
+                let Some((username, password)) = read_credentials(&tx, &mut ws_rx, "Enter a new username to register:").await else {
+                    return;
+                };
+                if password.is_empty() {
+                    tx.send(Message::text("Password cannot be empty. Try again.")).unwrap();
+                    continue;
+                }
+                match db.register_player(&username, &password).await {
+                    Ok(id) => {
+                        tx.send(Message::text(format!(
+                            "Registration successful! Welcome, {}! You are now in the Server.",
+                            username
+                        )))
+                        .unwrap();
+                        break (id, username, false);
+                    }
+                    Err(_) => {
+                        tx.send(Message::text("Registration failed. Try again.")).unwrap();
+                    }
+                }
+            }
+            "3" | "quit" => {
+                tx.send(Message::text("Goodbye!")).unwrap();
+                return;
+            }
+            "4" | "resume" => {
+                tx.send(Message::text("Enter your session token:")).unwrap();
+                let Some(token) = read_line(&mut ws_rx).await else {
+                    return;
+                };
+                match db.resume_session(&token).await {
+                    Ok(Some((id, username))) => {
+                        tx.send(Message::text(format!("Welcome back, {}! Your session has been resumed.", username))).unwrap();
+                        break (id, username, true);
                     }
                     _ => {
-                        tx.send(Message::text("Invalid option.")).unwrap();
+                        tx.send(Message::text("Session token is invalid or has expired.")).unwrap();
                     }
                 }
             }
+            _ => {
+                tx.send(Message::text("Invalid option.")).unwrap();
+            }
         }
+    };
+    // the same account cannot play from two connections at once
+    let existing = server_lobby
+        .lock()
+        .await
+        .players
+        .lock()
+        .await
+        .iter()
+        .find(|p| p.name == username)
+        .map(|p| (p.tx.clone(), p.session.clone()));
+    // a session whose socket dropped is always picked up again, whatever the policy
+    let reconnecting = existing.as_ref().is_some_and(|(_, session)| !session.is_connected());
+    if existing.is_some() && !reconnecting && duplicate_login_policy == DuplicateLoginPolicy::Reject {
+        tx.send(Message::text(format!("{} is already logged in on another connection.", username))).unwrap();
+        return;
     }
+
+    // a resumed session keeps its token, a new login gets one
+    if !resumed {
+        match db.create_session(&player_id).await {
+            Ok(token) => {
+                let _ = tx.send_event(&protocol::Event::SessionStarted { username: username.clone(), token });
+            }
+            Err(e) => eprintln!("Failed to create session for {}: {}", username, e),
+        }
+    }
+
+    if let Some((old_tx, old_session)) = existing {
+        // the old connection's task keeps running the session, now reading from and writing to this socket
+        old_tx.redirect(&tx, "You have logged in from another connection. Closing this one.");
+        if reconnecting {
+            tx.send(Message::text("You have reconnected. Your seat was held for you.")).unwrap();
+            println!("{} has reconnected.", username);
+        } else {
+            tx.send(Message::text("Your session has been moved to this connection.")).unwrap();
+            println!("{} has taken over their session from another connection.", username);
+        }
+        // catch the player up on the hand they are in
+        if let Some(table_state) = old_session.table_state() {
+            let _ = tx.send_event(&table_state);
+        }
+        old_session.take_over(ws_rx).await;
+        return;
+    }
+    // from here on a dropped socket holds the player's seat for a while
+    ws_rx.hold_session_for(protocol::RECONNECT_GRACE);
+
+    let mut current_player = Player {
+        name: username.clone(),
+        id: player_id,
+        hand: Vec::new(),
+        wallet: db.get_player_wallet(&username).await.unwrap_or(0),
+        tx: tx.clone(),
+        rx: Arc::new(Mutex::new(ws_rx)),
+        session,
+        state: lobby::IN_SERVER,
+        current_bet: 0,
+        total_bet: 0,
+        dealer: false,
+        ready: false,
+        time_bank_used: false,
+        games_played: 0,
+        games_won: 0,
+        lobby: server_lobby.clone(),
+    };
+    server_lobby.lock().await.add_player(current_player.clone()).await;
+    server_lobby.lock().await.broadcast(format!("{} has joined the server!", username)).await;
+    let username_id = username;
+    println!("{} has joined the server!", username_id.clone());
+    println!("server player count: {}", server_lobby.lock().await.current_player_count);
+    println!("Server players:\n{}\n\n", server_lobby.lock().await.get_player_names().await);
     sleep(Duration::from_secs(2)).await;
     
     let lobby_names = get_lobby_names(server_lobby.clone()).await;
     tx.send(Message::text(format!(
-        "Current Lobbies:\n{}\nChoose an option:\nCreate new lobby with lobby name\n\t1 [lobby name]\nJoin lobby with lobby name\n\t2 [lobby name]\nShow current lobbies\n\t3\nShow stats\n\t4\nShow commands\n\t5\nQuit\n\t6\n",
-        lobby_names
+        "Current Lobbies:\n{}\n{}",
+        lobby_names, SERVER_MENU
     )))
     .unwrap();
 
@@ -288,17 +561,41 @@ async fn handle_connection(ws: WebSocket, db: Arc<Database>, server_lobby: Arc<M
             }
             _ => {
                 match result.trim() {
-                    choice if choice.starts_with("1") => {
+                    // checked before option 1, which would also match "10"
+                    choice if choice == "10" || choice.starts_with("10 ") || choice.starts_with("pokerstats") => {
+                        // VIEW POKER STATS------------------------
+                        let player = choice.split_whitespace().nth(1).unwrap_or(&current_player.id);
+                        send_variant_stats(&tx, &db, player).await;
+                    }
+                    choice if choice.starts_with("1") || choice.starts_with("create ") => {
                         let lobby_name_input = choice.split(" ").collect::<Vec<&str>>();
-                        if lobby_name_input.len() != 2 {
+                        if lobby_name_input.len() < 2 || lobby_name_input.len() > 6 {
                             tx.send(Message::text("Invalid lobby name.")).unwrap();
                             continue;
                         }
-                        let lobby_name = choice.split(" ").collect::<Vec<&str>>()[1];
+                        let lobby_name = lobby_name_input[1];
+                        let variant = match variant::variant_from_name(lobby_name_input.get(2).copied().unwrap_or("draw")) {
+                            Some(variant) => variant,
+                            None => {
+                                tx.send(Message::text("Invalid game type. Choose draw, deuces, joker, holdem or badugi.")).unwrap();
+                                continue;
+                            }
+                        };
+                        let (forced_bets, bet_limit, action_clock) = match parse_lobby_settings(variant.as_ref(), &lobby_name_input[3.min(lobby_name_input.len())..]) {
+                            Ok(settings) => settings,
+                            Err(e) => {
+                                tx.send(Message::text(e)).unwrap();
+                                continue;
+                            }
+                        };
                         if server_lobby.lock().await.lobby_exists(lobby_name.to_string()).await {
                             tx.send(Message::text("Lobby name already exists.")).unwrap();
                         } else {
-                            let new_lobby = Arc::new(Mutex::new(Lobby::new(None, lobby_name.to_string()).await));
+                            let mut new_lobby = Lobby::new(None, lobby_name.to_string(), Some(variant)).await;
+                            new_lobby.forced_bets = forced_bets;
+                            new_lobby.bet_limit = bet_limit;
+                            new_lobby.action_clock = action_clock;
+                            let new_lobby = Arc::new(Mutex::new(new_lobby));
                             server_lobby.lock().await.add_lobby(new_lobby.clone()).await;
                             server_lobby.lock().await.broadcast(
                                 format!("{} has created a new lobby: {}", username_id.clone(), lobby_name)
@@ -306,7 +603,7 @@ async fn handle_connection(ws: WebSocket, db: Arc<Database>, server_lobby: Arc<M
                             println!("{} has created a new lobby: {}", username_id.clone(), lobby_name);
                         }
                     }
-                    choice if choice.starts_with("2") => {
+                    choice if choice.starts_with("2") || choice.starts_with("join ") => {
                         let lobby_name_input = choice.split(" ").collect::<Vec<&str>>();
                         if lobby_name_input.len() != 2 {
                             tx.send(Message::text("Invalid lobby name.")).unwrap();
@@ -343,34 +640,91 @@ async fn handle_connection(ws: WebSocket, db: Arc<Database>, server_lobby: Arc<M
                         )))
                         .unwrap();
                     }
-                    choice if choice.starts_with("4") => {
+                    choice if choice.starts_with("4") || choice.starts_with("stats") => {
                         // VIEW STATS------------------------
-                        // let stats = db.get_player_stats(&username_id).await.unwrap();
-                        // tx.send(Message::text(format!("Stats: {:?}", stats))).unwrap();
-                        let stats = db.player_stats(&username_id).await;
-                        if let Ok(stats) = stats {
-                            tx.send(Message::text(format!(
-                                "Player Stats for {}: Games Played: {}, Games Won: {}, Wallet: {}",
-                                &username_id, stats.games_played, stats.games_won, stats.wallet,
-                            )))
-                            .unwrap();
-                        } else {
-                            tx.send(Message::text("Failed to retrieve stats.")).unwrap();
-                        }
-
+                        // any player's stats by ID or name, or your own if none is given
+                        let player = choice.split_whitespace().nth(1).unwrap_or(&current_player.id);
+                        send_player_stats(&tx, &db, player).await;
                     }
                     choice if choice.starts_with("5") => {
                         let lobby_names = get_lobby_names(server_lobby.clone()).await;
                         tx.send(Message::text(format!(
-                            "Current Lobbies:\n\t{}\nChoose an option:\nCreate new lobby with lobby name\n\t1 [lobby name]\nJoin lobby with lobby name\n\t2 [lobby name]\nShow current lobbies\n\t3\nShow stats\n\t4\nShow commands\n\t5\nQuit\n\t6\n",
-                            lobby_names
+                            "Current Lobbies:\n{}\n{}",
+                            lobby_names, SERVER_MENU
                         )))
                         .unwrap();
                     }
-                    choice if choice.starts_with("6") => {
+                    choice if choice.starts_with("6") || choice == "quit" => {
                         tx.send(Message::text("Goodbye!")).unwrap();
                         break;
                     }
+                    choice if choice.starts_with("7") || choice.starts_with("game ") => {
+                        // GAME RESULT------------------------
+                        let Some(Ok(game_number)) = choice.split_whitespace().nth(1).map(str::parse::<i64>) else {
+                            tx.send(Message::text("Usage: 7 [game number]")).unwrap();
+                            continue;
+                        };
+                        match db.game_result(game_number, &username_id).await {
+                            Ok(Some(record)) => {
+                                let outcome = if record.result.won { "won" } else { "lost" };
+                                let hand = record.result.final_hand.unwrap_or_else(|| "folded".to_string());
+                                tx.send(Message::text(format!(
+                                    "Game #{} ({} in {}, started {}): you {} with {}, net result {:+}",
+                                    record.game_number, record.variant, record.lobby, record.started_at, outcome, hand, record.result.net_result,
+                                )))
+                                .unwrap();
+                            }
+                            Ok(None) => {
+                                tx.send(Message::text(format!("You have no result in game #{}.", game_number))).unwrap();
+                            }
+                            Err(_) => {
+                                tx.send(Message::text("Failed to retrieve the game.")).unwrap();
+                            }
+                        }
+                    }
+                    choice if choice.starts_with("9") || choice.starts_with("leaderboard") => {
+                        // LEADERBOARD------------------------
+                        let mut args = choice.split_whitespace().skip(1);
+                        let metric = args.next().map_or(Ok(protocol::LeaderboardMetric::Wallet), str::parse);
+                        let page = args.next().map_or(Ok(1), str::parse::<u32>);
+                        let (metric, page) = match (metric, page) {
+                            (Ok(metric), Ok(page)) if page > 0 => (metric, page),
+                            (Err(message), _) => {
+                                tx.send(Message::text(message)).unwrap();
+                                continue;
+                            }
+                            _ => {
+                                tx.send(Message::text("Usage: 9 [metric] [page]")).unwrap();
+                                continue;
+                            }
+                        };
+                        match db.leaderboard(metric, page, api::DEFAULT_PAGE_SIZE).await {
+                            Ok(entries) => {
+                                let entries = entries.into_iter().map(Into::into).collect();
+                                let _ = tx.send_event(&protocol::Event::Leaderboard { metric, page, entries });
+                            }
+                            Err(_) => {
+                                tx.send(Message::text("Failed to retrieve the leaderboard.")).unwrap();
+                            }
+                        }
+                    }
+                    choice if choice.starts_with("8") || choice == "reset" => {
+                        // RESET GAME HISTORY------------------------
+                        if !db.is_admin(&username_id).await.unwrap_or(false) {
+                            tx.send(Message::text("Only administrators can reset the game numbering.")).unwrap();
+                            continue;
+                        }
+                        let status = server_lobby.lock().await.reset_history().await;
+                        match status {
+                            lobby::SUCCESS => println!("{} reset the game numbering.", username_id),
+                            lobby::GAME_IN_PROGRESS => {
+                                tx.send(Message::text("A game is in progress. Try again once every game has finished.")).unwrap();
+                            }
+                            _ => {
+                                tx.send(Message::text("Failed to reset the game numbering.")).unwrap();
+                            }
+                        }
+                    }
                     _ => {
                         tx.send(Message::text("Invalid option.")).unwrap();
                     }
@@ -403,20 +757,22 @@ async fn join_lobby(server_lobby: Arc<Mutex<Lobby>>, mut player: Player, db: Arc
     println!("{} has joined lobby: {}", player.name, player_lobby.lock().await.name);
     
     tx.send(Message::text(format!(
-        "Welcome to lobby: {}\nChoose an option:\n1. Ready:           r\n2. Show Players:    p\n3. View stats:      s\n4. Quit:            q\n\n",
+        "Welcome to lobby: {}\nChoose an option:\n1. Ready:           r\n2. Show Players:    p\n3. View stats:      s [player ID or name]\n4. Quit:            q\n\n",
         player_lobby.lock().await.name
     )))
     .unwrap();
 
     loop {
         let result = player.get_player_input().await;
-        let lobby_state = player_lobby.lock().await.game_state.clone();
+        let lobby_state = player_lobby.lock().await.game_state;
         let lobby_name = player_lobby.lock().await.name.clone();
         println!("Lobby {} state: {}", lobby_name, lobby_state);
     
         if lobby_state == lobby::JOINABLE || lobby_state == lobby::GAME_LOBBY_FULL {
             match result.as_str() {
                 "Disconnect" => {
+                    // the lobby's copy of the player is the one games and resets keep up to date
+                    let lobby_player = player_lobby.lock().await.players.lock().await.iter().find(|p| p.name == player.name).cloned();
                     let lobby_status = player_lobby.lock().await.remove_player(player.name.clone()).await;
                     println!("lobby status: {}", lobby_status);
                     if lobby_status == lobby::GAME_LOBBY_EMPTY {
@@ -427,7 +783,7 @@ async fn join_lobby(server_lobby: Arc<Mutex<Lobby>>, mut player: Player, db: Arc
                     if player_lobby.lock().await.game_state == lobby::JOINABLE {
                         player_lobby.lock().await.ready_up("".to_string()).await;
                     }
-                    if let Err(e) = db.update_player_stats(&player).await {
+                    if let Err(e) = db.update_player_stats(lobby_player.as_ref().unwrap_or(&player)).await {
                         eprintln!("Failed to update player stats: {}", e);
                     }
                     drop(player.rx);
@@ -443,22 +799,12 @@ async fn join_lobby(server_lobby: Arc<Mutex<Lobby>>, mut player: Player, db: Arc
                             tx.send(Message::text(format!("Players:\n{}", players)))
                                 .unwrap();
                         }
-                        "s" => {
+                        choice if choice == "s" || choice.starts_with("s ") || choice.starts_with("stats") => {
                             // VIEW STATS------------------------
-                            // let stats = db.get_player_stats(&username_id).await.unwrap();
-                            // tx.send(Message::text(format!("Stats: {:?}", stats))).unwrap();
-                            let stats = db.player_stats(&player.name).await;
-                        if let Ok(stats) = stats {
-                            tx.send(Message::text(format!(
-                                "Player Stats for {}: Games Played: {}, Games Won: {}, Wallet: {}",
-                                &player.name, stats.games_played, stats.games_won, stats.wallet,
-                            )))
-                            .unwrap();
-                        } else {
-                            tx.send(Message::text("Failed to retrieve stats.")).unwrap();
-                        }
+                            let target = choice.split_whitespace().nth(1).unwrap_or(&player.id);
+                            send_player_stats(&tx, &db, target).await;
                         }
-                        "q" => {
+                        "q" | "leave" => {
                             // QUIT LOBBY------------------------
                             let lobby_status = player_lobby.lock().await.remove_player(player.name.clone()).await;
                             if lobby_status == lobby::GAME_LOBBY_EMPTY {
@@ -467,7 +813,7 @@ async fn join_lobby(server_lobby: Arc<Mutex<Lobby>>, mut player: Player, db: Arc
                             // update player stat to DB
                             return "Normal".to_string();
                         }
-                        "r" => {
+                        "r" | "ready" => {
                             // READY UP------------------------
                             let mut all_ready = 0;
                             player.ready = true;
@@ -0,0 +1,267 @@
+//! A module for splitting the chips in the middle into a main pot and side pots
+//!
+//! When a player goes all-in for less than the other players have bet, they can only win back as much from each opponent as they put in themselves. Everything bet above that amount goes into a side pot that only the players who matched it can win.
+//!
+//! Pots are built from the total each player contributed over the whole hand. Every distinct all-in amount among the players still in the hand closes off one pot, and a player is eligible for a pot only if they did not fold and contributed at least up to its level. Chips put in by players who later folded still count towards the pots they reached.
+
+/// A single pot and the players who can win it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pot {
+    pub amount: i32,
+    /// Names of the players who can win this pot, in seat order.
+    pub eligible: Vec<String>,
+}
+
+/// A player's stake in the hand, as needed to build the pots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contribution {
+    pub name: String,
+    /// Total chips the player put into the pot this hand.
+    pub amount: i32,
+    pub folded: bool,
+}
+
+/// Splits the chips contributed over a hand into the main pot followed by any side pots.
+///
+/// # Arguments
+/// * `contributions` - Every player's contribution to the hand, in seat order.
+///
+/// # Returns
+/// * `Vec<Pot>` - The main pot first, then each side pot in the order it was created. Empty if nobody is left to win the chips.
+pub fn build_pots(contributions: &[Contribution]) -> Vec<Pot> {
+    // each distinct amount put in by a player still in the hand closes off a pot
+    let mut levels: Vec<i32> = contributions
+        .iter()
+        .filter(|c| !c.folded && c.amount > 0)
+        .map(|c| c.amount)
+        .collect();
+    levels.sort();
+    levels.dedup();
+
+    let mut pots: Vec<Pot> = Vec::new();
+    let mut previous_level = 0;
+    for &level in levels.iter() {
+        let amount: i32 = contributions
+            .iter()
+            .map(|c| c.amount.min(level) - c.amount.min(previous_level))
+            .sum();
+        let eligible = contributions
+            .iter()
+            .filter(|c| !c.folded && c.amount >= level)
+            .map(|c| c.name.clone())
+            .collect();
+        pots.push(Pot { amount, eligible });
+        previous_level = level;
+    }
+
+    // chips a folded player put in above every remaining player's stake go to the last pot
+    let leftover: i32 = contributions
+        .iter()
+        .map(|c| c.amount - c.amount.min(previous_level))
+        .sum();
+    if let Some(last) = pots.last_mut() {
+        last.amount += leftover;
+    }
+    pots
+}
+
+/// The players who won one pot and the hand they won it with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Award<H> {
+    /// Seat indices of the winners, in seat order.
+    pub winners: Vec<usize>,
+    /// The winning hand, or `None` if the pot was uncontested.
+    pub hand: Option<H>,
+}
+
+/// Decides who wins each pot.
+///
+/// A pot with a single eligible player goes to them without looking at any cards, so a hand that everyone else folded before the board was dealt never has its hole cards evaluated on their own.
+///
+/// # Arguments
+/// * `pots` - The pots built for the hand.
+/// * `names` - Every player's name, in seat order.
+/// * `evaluate` - Evaluates the hand of the player in the given seat. Called at most once per player, and only for contested pots.
+///
+/// # Returns
+/// * `Vec<Award<H>>` - The award for each pot, in the same order as the pots.
+pub fn award_pots<H: Ord + Clone>(pots: &[Pot], names: &[String], mut evaluate: impl FnMut(usize) -> H) -> Vec<Award<H>> {
+    let mut hands: Vec<Option<H>> = vec![None; names.len()];
+    pots.iter()
+        .map(|pot| {
+            let eligible: Vec<usize> = (0..names.len()).filter(|&i| pot.eligible.contains(&names[i])).collect();
+            if eligible.len() < 2 {
+                return Award { winners: eligible, hand: None };
+            }
+            for &i in eligible.iter() {
+                if hands[i].is_none() {
+                    hands[i] = Some(evaluate(i));
+                }
+            }
+            let hand = eligible.iter().filter_map(|&i| hands[i].clone()).max();
+            let winners = eligible.into_iter().filter(|&i| hands[i] == hand).collect();
+            Award { winners, hand }
+        })
+        .collect()
+}
+
+/// Divides a pot between its winners.
+///
+/// Chips that cannot be split evenly go one each to the first winners in seat order.
+///
+/// # Arguments
+/// * `amount` - The size of the pot.
+/// * `winner_count` - The number of players sharing it.
+///
+/// # Returns
+/// * `Vec<i32>` - Each winner's share, in the same order as the winners.
+pub fn split_pot(amount: i32, winner_count: usize) -> Vec<i32> {
+    if winner_count == 0 {
+        return Vec::new();
+    }
+    let share = amount / winner_count as i32;
+    let odd_chips = (amount % winner_count as i32) as usize;
+    (0..winner_count)
+        .map(|i| if i < odd_chips { share + 1 } else { share })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contribution(name: &str, amount: i32, folded: bool) -> Contribution {
+        Contribution { name: name.to_string(), amount, folded }
+    }
+
+    fn names(pot: &Pot) -> Vec<&str> {
+        pot.eligible.iter().map(|name| name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_single_pot_without_all_in() {
+        let pots = build_pots(&[
+            contribution("alice", 50, false),
+            contribution("bob", 50, false),
+            contribution("carol", 50, false),
+        ]);
+        assert_eq!(pots.len(), 1);
+        assert_eq!(pots[0].amount, 150);
+        assert_eq!(names(&pots[0]), vec!["alice", "bob", "carol"]);
+    }
+
+    #[test]
+    fn test_short_all_in_creates_side_pot() {
+        // alice is all-in for 30, bob and carol keep betting to 100
+        let pots = build_pots(&[
+            contribution("alice", 30, false),
+            contribution("bob", 100, false),
+            contribution("carol", 100, false),
+        ]);
+        assert_eq!(pots.len(), 2);
+        assert_eq!(pots[0].amount, 90);
+        assert_eq!(names(&pots[0]), vec!["alice", "bob", "carol"]);
+        assert_eq!(pots[1].amount, 140);
+        assert_eq!(names(&pots[1]), vec!["bob", "carol"]);
+    }
+
+    #[test]
+    fn test_multiple_all_ins() {
+        let pots = build_pots(&[
+            contribution("alice", 20, false),
+            contribution("bob", 50, false),
+            contribution("carol", 80, false),
+            contribution("dave", 80, false),
+        ]);
+        let amounts: Vec<i32> = pots.iter().map(|pot| pot.amount).collect();
+        assert_eq!(amounts, vec![80, 90, 60]);
+        assert_eq!(names(&pots[1]), vec!["bob", "carol", "dave"]);
+        assert_eq!(names(&pots[2]), vec!["carol", "dave"]);
+    }
+
+    #[test]
+    fn test_folded_chips_stay_in_the_pots() {
+        // dave folded after putting in 60, which spans both pots
+        let pots = build_pots(&[
+            contribution("alice", 40, false),
+            contribution("bob", 100, false),
+            contribution("carol", 100, false),
+            contribution("dave", 60, true),
+        ]);
+        assert_eq!(pots.len(), 2);
+        assert_eq!(pots[0].amount, 160);
+        assert_eq!(names(&pots[0]), vec!["alice", "bob", "carol"]);
+        assert_eq!(pots[1].amount, 140);
+        assert_eq!(names(&pots[1]), vec!["bob", "carol"]);
+    }
+
+    #[test]
+    fn test_uncalled_chips_only_go_to_their_owner() {
+        // bob bet more than anyone could call, so the excess is a pot only bob can win
+        let pots = build_pots(&[contribution("alice", 30, false), contribution("bob", 70, false)]);
+        assert_eq!(pots[1].amount, 40);
+        assert_eq!(names(&pots[1]), vec!["bob"]);
+    }
+
+    #[test]
+    fn test_folded_player_above_every_stake() {
+        // carol raised and then disconnected, nobody remaining matched her
+        let pots = build_pots(&[
+            contribution("alice", 10, false),
+            contribution("bob", 10, true),
+            contribution("carol", 50, true),
+        ]);
+        assert_eq!(pots.len(), 1);
+        assert_eq!(pots[0].amount, 70);
+        assert_eq!(names(&pots[0]), vec!["alice"]);
+    }
+
+    #[test]
+    fn test_pots_add_up_to_contributions() {
+        let contributions = [
+            contribution("alice", 15, false),
+            contribution("bob", 90, true),
+            contribution("carol", 45, false),
+            contribution("dave", 120, false),
+            contribution("erin", 45, false),
+        ];
+        let total: i32 = contributions.iter().map(|c| c.amount).sum();
+        let pots = build_pots(&contributions);
+        assert_eq!(pots.iter().map(|pot| pot.amount).sum::<i32>(), total);
+        for pot in pots.iter() {
+            assert!(!pot.eligible.contains(&"bob".to_string()));
+        }
+    }
+
+    #[test]
+    fn test_uncontested_pot_is_not_evaluated() {
+        // everyone but bob folded preflop, so his two hole cards must never be evaluated
+        let names = vec!["alice".to_string(), "bob".to_string(), "carol".to_string()];
+        let pots = build_pots(&[contribution("alice", 5, true), contribution("bob", 10, false), contribution("carol", 10, true)]);
+        let awards = award_pots(&pots, &names, |_| -> i32 { panic!("an uncontested pot was evaluated") });
+        assert_eq!(awards, vec![Award { winners: vec![1], hand: None }]);
+    }
+
+    #[test]
+    fn test_award_pots() {
+        // alice has the best hand but is only eligible for the main pot, bob and carol tie for the side pot
+        let names = vec!["alice".to_string(), "bob".to_string(), "carol".to_string()];
+        let pots = build_pots(&[contribution("alice", 30, false), contribution("bob", 100, false), contribution("carol", 100, false)]);
+        let mut evaluated = Vec::new();
+        let awards = award_pots(&pots, &names, |i| {
+            evaluated.push(i);
+            [3, 2, 2][i]
+        });
+        assert_eq!(awards[0], Award { winners: vec![0], hand: Some(3) });
+        assert_eq!(awards[1], Award { winners: vec![1, 2], hand: Some(2) });
+        assert_eq!(evaluated, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_split_pot() {
+        assert_eq!(split_pot(100, 2), vec![50, 50]);
+        assert_eq!(split_pot(100, 3), vec![34, 33, 33]);
+        assert_eq!(split_pot(5, 1), vec![5]);
+        assert!(split_pot(10, 0).is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+//! A module for the messages exchanged with clients over the WebSocket
+//!
+//! Every connection talks one of two protocols, chosen when the WebSocket is opened:
+//! - Text, the default: numbered menus and prose, meant for people typing into a tool like `wscat`.
+//! - JSON, for GUI clients: typed requests in and typed events out, one JSON object per WebSocket message.
+//!
+//! The requests and events themselves are defined in the `poker-protocol` crate, which clients share. This module sends and receives them over a connection, and can move a logged in player's session from one connection to another.
+//!
+//! When a logged in player's socket drops, their session is held for `RECONNECT_GRACE`. Logging in or resuming the session from a new connection within that time picks the session up where it was, seat and all; only once it runs out do the menus see the player as disconnected. The server turns each request into the text command it stands for, so both protocols drive exactly the same menus.
+use futures_util::stream::SplitStream;
+use futures_util::StreamExt;
+pub use poker_protocol::{negotiate, schema, ActionKind, Event, LeaderboardEntry, LeaderboardMetric, PlayerStats, PotResult, Protocol, Request, VariantStats};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, error::SendError, UnboundedReceiver, UnboundedSender};
+use tokio::time::{timeout_at, Duration, Instant};
+use warp::ws::{Message, WebSocket};
+
+/// How long a logged in player's session is held after their socket drops.
+pub const RECONNECT_GRACE: Duration = Duration::from_secs(60);
+
+/// What a socket delivers to an inbox.
+#[derive(Debug)]
+enum Incoming {
+    Message(Result<Message, warp::Error>),
+    /// The socket closed or failed.
+    Dropped,
+    /// A new connection took the session over.
+    Reconnected,
+}
+
+/// The socket an outbox writes to.
+#[derive(Debug)]
+struct Target {
+    tx: UnboundedSender<Message>,
+    protocol: Protocol,
+}
+
+/// The sending half of a connection, which writes every message in the connection's protocol.
+///
+/// Clones share their socket, so redirecting one outbox redirects every copy of the player that holds it.
+#[derive(Debug, Clone)]
+pub struct Outbox {
+    target: Arc<Mutex<Target>>,
+}
+
+impl Outbox {
+    pub fn new(tx: UnboundedSender<Message>, protocol: Protocol) -> Self {
+        Outbox { target: Arc::new(Mutex::new(Target { tx, protocol })) }
+    }
+
+    fn protocol(&self) -> Protocol {
+        self.target.lock().unwrap().protocol
+    }
+
+    /// Sends a message. For JSON clients, plain text is wrapped in a `message` event.
+    pub fn send(&self, message: Message) -> Result<(), SendError<Message>> {
+        match (self.protocol(), message.to_str()) {
+            (Protocol::Json { .. }, Ok(text)) => self.send_event(&Event::Message { text: text.to_string() }),
+            _ => self.target.lock().unwrap().tx.send(message),
+        }
+    }
+
+    /// Sends an event, as prose for text clients or as JSON.
+    pub fn send_event(&self, event: &Event) -> Result<(), SendError<Message>> {
+        let target = self.target.lock().unwrap();
+        match target.protocol {
+            Protocol::Text => target.tx.send(Message::text(event.to_text())),
+            Protocol::Json { version } => target.tx.send(Message::text(event.to_json(version))),
+        }
+    }
+
+    /// Points this outbox, and every clone of it, at the socket `to` writes to. The old socket is sent `reason` and closed.
+    pub fn redirect(&self, to: &Outbox, reason: &str) {
+        if Arc::ptr_eq(&self.target, &to.target) {
+            return;
+        }
+        let _ = self.send(Message::text(reason));
+        let new_target = to.target.lock().unwrap();
+        let mut target = self.target.lock().unwrap();
+        let _ = target.tx.send(Message::close());
+        target.tx = new_target.tx.clone();
+        target.protocol = new_target.protocol;
+    }
+}
+
+/// A player's session, which outlives the connection it started on.
+///
+/// Clones share the session. A new connection can take it over, after which the player's inbox reads from the new socket and the old socket is ignored.
+#[derive(Debug, Clone)]
+pub struct Session {
+    tx: UnboundedSender<Incoming>,
+    // bumped on every takeover, so the sockets it replaced stop delivering
+    generation: Arc<AtomicU64>,
+    connected: Arc<AtomicBool>,
+    table_state: Arc<Mutex<Option<Event>>>,
+}
+
+impl Session {
+    /// Whether the session's socket is still open.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// The `table_state` event for the hand the player is in, if any.
+    pub fn table_state(&self) -> Option<Event> {
+        self.table_state.lock().unwrap().clone()
+    }
+
+    /// Records the table as the player sees it, to be sent if they reconnect. `None` once the hand is over.
+    pub fn set_table_state(&self, state: Option<Event>) {
+        *self.table_state.lock().unwrap() = state;
+    }
+
+    /// Delivers the messages read by `inbox`, a newer connection, to this session's inbox until that connection closes.
+    pub async fn take_over(&self, mut inbox: Inbox) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.connected.store(true, Ordering::SeqCst);
+        let _ = self.tx.send(Incoming::Reconnected);
+        while let Some(incoming) = inbox.rx.recv().await {
+            // a later takeover replaced this connection too
+            if self.generation.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            if let Incoming::Dropped = incoming {
+                self.connected.store(false, Ordering::SeqCst);
+            }
+            if self.tx.send(incoming).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Starts reading a socket.
+///
+/// # Arguments
+/// * `stream` - The socket's incoming half.
+/// * `outbox` - The connection's outbox, used to report errors in JSON requests.
+///
+/// # Returns
+/// * `(Inbox, Session)` - The inbox to read the socket's messages from, and the session they belong to.
+pub fn listen(mut stream: SplitStream<WebSocket>, outbox: Outbox) -> (Inbox, Session) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let session = Session {
+        tx,
+        generation: Arc::new(AtomicU64::new(0)),
+        connected: Arc::new(AtomicBool::new(true)),
+        table_state: Arc::new(Mutex::new(None)),
+    };
+    let reader = session.clone();
+    tokio::spawn(async move {
+        loop {
+            let message = stream.next().await;
+            if reader.generation.load(Ordering::SeqCst) != 0 {
+                return;
+            }
+            match message {
+                Some(Ok(msg)) if !msg.is_close() => {
+                    if reader.tx.send(Incoming::Message(Ok(msg))).is_err() {
+                        return;
+                    }
+                }
+                Some(Err(e)) => {
+                    eprintln!("Connection error: {}", e);
+                    break;
+                }
+                _ => break,
+            }
+        }
+        reader.connected.store(false, Ordering::SeqCst);
+        let _ = reader.tx.send(Incoming::Dropped);
+    });
+    let inbox = Inbox { rx, outbox, pending: VecDeque::new(), grace: Duration::ZERO, dropped_at: None };
+    (inbox, session)
+}
+
+/// The receiving half of a connection, which turns JSON requests into the text commands the menus read.
+pub struct Inbox {
+    rx: UnboundedReceiver<Incoming>,
+    outbox: Outbox,
+    pending: VecDeque<String>,
+    grace: Duration,
+    dropped_at: Option<Instant>,
+}
+
+impl Inbox {
+    /// Holds the session for `grace` whenever the socket drops, before reporting it closed.
+    pub fn hold_session_for(&mut self, grace: Duration) {
+        self.grace = grace;
+    }
+
+    /// Waits for the next message from the client.
+    ///
+    /// # Returns
+    /// * `Some(Ok(Message))` - The next message. For JSON clients, text messages are the commands translated from their requests. A close message means the socket dropped and no new connection took over in time.
+    /// * `Some(Err(warp::Error))` - If the connection failed.
+    /// * `None` - If the connection is closed.
+    pub async fn next_message(&mut self) -> Option<Result<Message, warp::Error>> {
+        loop {
+            if let Some(command) = self.pending.pop_front() {
+                return Some(Ok(Message::text(command)));
+            }
+            let incoming = match self.dropped_at {
+                Some(dropped_at) => match timeout_at(dropped_at + self.grace, self.rx.recv()).await {
+                    Ok(incoming) => incoming?,
+                    Err(_) => return Some(Ok(Message::close())),
+                },
+                None => self.rx.recv().await?,
+            };
+            let message = match incoming {
+                Incoming::Message(message) => message,
+                Incoming::Dropped => {
+                    // wait for the player to come back, keeping the first drop's deadline
+                    self.dropped_at.get_or_insert_with(Instant::now);
+                    continue;
+                }
+                Incoming::Reconnected => {
+                    self.dropped_at = None;
+                    continue;
+                }
+            };
+            let text = match (&message, self.outbox.protocol()) {
+                (Ok(msg), Protocol::Json { .. }) if msg.is_text() => msg.to_str().unwrap_or_default(),
+                _ => return Some(message),
+            };
+            let commands = serde_json::from_str::<Request>(text)
+                .map_err(|e| format!("Invalid request: {}", e))
+                .and_then(|request| request.to_commands());
+            match commands {
+                Ok(commands) => self.pending.extend(commands),
+                Err(message) => {
+                    let _ = self.outbox.send_event(&Event::Error { message });
+                }
+            }
+        }
+    }
+}
+
+/// An inbox and session that are not attached to a socket, with messages sent to the client collected in the returned receiver.
+#[cfg(test)]
+pub(crate) fn detached(grace: Duration) -> (Inbox, Session, UnboundedReceiver<Message>) {
+    let (out_tx, out_rx) = mpsc::unbounded_channel();
+    let (tx, rx) = mpsc::unbounded_channel();
+    let session = Session {
+        tx,
+        generation: Arc::new(AtomicU64::new(0)),
+        connected: Arc::new(AtomicBool::new(true)),
+        table_state: Arc::new(Mutex::new(None)),
+    };
+    let outbox = Outbox::new(out_tx, Protocol::Text);
+    let inbox = Inbox { rx, outbox, pending: VecDeque::new(), grace, dropped_at: None };
+    (inbox, session, out_rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dropped_socket_is_held_for_the_grace_period() {
+        let (mut inbox, session, _out) = detached(Duration::from_millis(100));
+        session.tx.send(Incoming::Dropped).unwrap();
+        let start = Instant::now();
+        let message = inbox.next_message().await.unwrap().unwrap();
+        assert!(message.is_close());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_new_connection_takes_over_the_session() {
+        let (mut inbox, session, _out) = detached(Duration::from_secs(60));
+        session.tx.send(Incoming::Dropped).unwrap();
+        session.connected.store(false, Ordering::SeqCst);
+
+        let (new_inbox, new_session, _new_out) = detached(Duration::ZERO);
+        new_session.tx.send(Incoming::Message(Ok(Message::text("3")))).unwrap();
+        drop(new_session);
+        session.take_over(new_inbox).await;
+
+        assert!(session.is_connected());
+        assert_eq!(inbox.next_message().await.unwrap().unwrap().to_str(), Ok("3"));
+    }
+
+    #[tokio::test]
+    async fn test_redirected_outbox_writes_to_the_new_socket() {
+        let (old_tx, mut old_rx) = mpsc::unbounded_channel();
+        let (new_tx, mut new_rx) = mpsc::unbounded_channel();
+        let old = Outbox::new(old_tx, Protocol::Text);
+        let copy = old.clone();
+        old.redirect(&Outbox::new(new_tx, Protocol::Json { version: 1 }), "Moved.");
+
+        assert_eq!(old_rx.recv().await.unwrap().to_str(), Ok("Moved."));
+        assert!(old_rx.recv().await.unwrap().is_close());
+        // every copy of the player follows the redirect, in the new socket's protocol
+        copy.send(Message::text("hi")).unwrap();
+        let json = new_rx.recv().await.unwrap();
+        assert_eq!(json.to_str(), Ok(r#"{"version":1,"type":"message","text":"hi"}"#));
+    }
+}
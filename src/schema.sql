@@ -1,7 +1,59 @@
-CREATE TABLE players (
+-- applied at every startup, so every statement must be safe to run on an existing database;
+-- a column added to an existing table also goes in ADDED_COLUMNS in src/database/mod.rs
+
+CREATE TABLE IF NOT EXISTS players (
     id TEXT PRIMARY KEY,
     name TEXT UNIQUE NOT NULL,
     games_played INTEGER DEFAULT 0,
     games_won INTEGER DEFAULT 0,
-    wallet INTEGER DEFAULT 0
+    wallet INTEGER DEFAULT 0,
+    -- argon2 hash in PHC string format, NULL for accounts made before passwords were added
+    password_hash TEXT,
+    -- administrators can reset the game numbering and player stats
+    is_admin INTEGER NOT NULL DEFAULT 0
+);
+
+-- only a SHA-256 hash of each session token is stored
+CREATE TABLE IF NOT EXISTS sessions (
+    token_hash TEXT PRIMARY KEY,
+    player_id TEXT NOT NULL REFERENCES players(id),
+    expires_at INTEGER NOT NULL
+);
+
+-- one row per hand, numbered sequentially from 0
+CREATE TABLE IF NOT EXISTS games (
+    game_number INTEGER PRIMARY KEY,
+    variant TEXT NOT NULL,
+    lobby TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    -- every chip put into the pot over the game
+    pot INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS game_participants (
+    game_number INTEGER NOT NULL REFERENCES games(game_number),
+    player_id TEXT NOT NULL REFERENCES players(id),
+    net_result INTEGER NOT NULL,
+    -- the hand shown at the end in short notation, NULL if the player folded
+    final_hand TEXT,
+    won INTEGER NOT NULL DEFAULT 0,
+    -- whether the player was still in the hand when it was shown down
+    showdown INTEGER NOT NULL DEFAULT 0,
+    -- chips the player took from the pots they won
+    collected INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (game_number, player_id)
+);
+
+-- every move made in a betting round, in order; forced bets are not moves
+CREATE TABLE IF NOT EXISTS game_actions (
+    game_number INTEGER NOT NULL REFERENCES games(game_number),
+    seq INTEGER NOT NULL,
+    player_id TEXT NOT NULL REFERENCES players(id),
+    -- 0 for the first betting round, before any community cards or draws
+    betting_round INTEGER NOT NULL,
+    -- check, call, bet, raise or fold; an all-in is recorded as the call, bet or raise it amounts to
+    action TEXT NOT NULL,
+    amount INTEGER,
+    PRIMARY KEY (game_number, seq)
 );
@@ -0,0 +1,281 @@
+//! A module for describing the poker variants a lobby can host
+//! 
+//! This module contains the `PokerVariant` trait, which declares the phases a hand moves through, how many cards each player is dealt, and how hands are evaluated at the showdown. A lobby drives whichever variant it was created with, so adding a new game only means implementing the trait.
+//! 
+//! The variants currently supported are five-card draw (also played deuces wild or with a joker), Texas Hold'em and Badugi.
+use crate::betting::{ForcedBets, DEFAULT_ANTE, DEFAULT_BIG_BLIND, DEFAULT_SMALL_BLIND};
+use crate::card::{Card, Rank};
+use crate::evaluator::{get_badugi_hand_type, get_best_hand, get_hand_type, get_wild_hand_type, HandValue};
+use std::sync::Arc;
+
+/// A single step in the flow of a hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// The ante or the blinds go into the pot, depending on the lobby's stakes.
+    ForcedBets,
+    /// Shuffle the deck and deal each player `hand_size` private cards.
+    DealHoleCards,
+    /// Deal shared cards face up on the board.
+    DealCommunityCards { name: &'static str, count: usize },
+    /// A round of betting.
+    Bet,
+    /// Players may exchange any of their cards for new ones.
+    Draw,
+    /// Remaining players reveal their hands and the pot is awarded.
+    Showdown,
+}
+
+/// A poker variant that a lobby can host.
+pub trait PokerVariant: Send + Sync {
+    /// The display name of the variant.
+    fn name(&self) -> &'static str;
+
+    /// The phases of a single hand, in order.
+    fn phases(&self) -> Vec<Phase>;
+
+    /// The number of private cards dealt to each player.
+    fn hand_size(&self) -> usize;
+
+    /// Scores a player's hand together with any community cards on the board.
+    fn evaluate(&self, hand: &[Card], community_cards: &[Card]) -> HandValue;
+
+    /// The number of jokers added to the deck.
+    fn jokers(&self) -> usize {
+        0
+    }
+
+    /// The stakes a lobby plays this variant with unless it asks for others.
+    fn forced_bets(&self) -> ForcedBets {
+        ForcedBets::Ante(DEFAULT_ANTE)
+    }
+}
+
+/// Standard five-card draw: one draw between two betting rounds.
+pub struct FiveCardDraw;
+
+impl PokerVariant for FiveCardDraw {
+    fn name(&self) -> &'static str {
+        "Five Card Draw"
+    }
+
+    fn phases(&self) -> Vec<Phase> {
+        vec![
+            Phase::ForcedBets,
+            Phase::DealHoleCards,
+            Phase::Bet,
+            Phase::Draw,
+            Phase::Bet,
+            Phase::Showdown,
+        ]
+    }
+
+    fn hand_size(&self) -> usize {
+        5
+    }
+
+    fn evaluate(&self, hand: &[Card], _community_cards: &[Card]) -> HandValue {
+        HandValue::Poker(get_hand_type(hand))
+    }
+}
+
+/// Five-card draw played with wild cards: jokers added to the deck, or whole ranks declared wild.
+pub struct WildFiveCardDraw {
+    pub name: &'static str,
+    pub jokers: usize,
+    pub wild_ranks: Vec<Rank>,
+}
+
+impl WildFiveCardDraw {
+    /// Five-card draw where every Two is wild.
+    pub fn deuces_wild() -> Self {
+        WildFiveCardDraw { name: "Five Card Draw (Deuces Wild)", jokers: 0, wild_ranks: vec![Rank::Two] }
+    }
+
+    /// Five-card draw with a single joker added to the deck as a wild card.
+    pub fn with_joker() -> Self {
+        WildFiveCardDraw { name: "Five Card Draw (Joker Wild)", jokers: 1, wild_ranks: Vec::new() }
+    }
+}
+
+impl PokerVariant for WildFiveCardDraw {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn phases(&self) -> Vec<Phase> {
+        FiveCardDraw.phases()
+    }
+
+    fn hand_size(&self) -> usize {
+        5
+    }
+
+    fn evaluate(&self, hand: &[Card], _community_cards: &[Card]) -> HandValue {
+        HandValue::Poker(get_wild_hand_type(hand, &self.wild_ranks))
+    }
+
+    fn jokers(&self) -> usize {
+        self.jokers
+    }
+}
+
+/// Texas Hold'em: two hole cards, then the flop, turn and river with a betting round after each.
+pub struct TexasHoldem;
+
+impl PokerVariant for TexasHoldem {
+    fn name(&self) -> &'static str {
+        "Texas Hold'em"
+    }
+
+    fn phases(&self) -> Vec<Phase> {
+        vec![
+            Phase::ForcedBets,
+            Phase::DealHoleCards,
+            Phase::Bet,
+            Phase::DealCommunityCards { name: "flop", count: 3 },
+            Phase::Bet,
+            Phase::DealCommunityCards { name: "turn", count: 1 },
+            Phase::Bet,
+            Phase::DealCommunityCards { name: "river", count: 1 },
+            Phase::Bet,
+            Phase::Showdown,
+        ]
+    }
+
+    fn hand_size(&self) -> usize {
+        2
+    }
+
+    fn evaluate(&self, hand: &[Card], community_cards: &[Card]) -> HandValue {
+        HandValue::Poker(get_best_hand(&[hand, community_cards].concat()).0)
+    }
+
+    fn forced_bets(&self) -> ForcedBets {
+        ForcedBets::Blinds { small: DEFAULT_SMALL_BLIND, big: DEFAULT_BIG_BLIND }
+    }
+}
+
+/// Badugi: four-card lowball with three draws and four betting rounds.
+pub struct Badugi;
+
+impl PokerVariant for Badugi {
+    fn name(&self) -> &'static str {
+        "Badugi"
+    }
+
+    fn phases(&self) -> Vec<Phase> {
+        vec![
+            Phase::ForcedBets,
+            Phase::DealHoleCards,
+            Phase::Bet,
+            Phase::Draw,
+            Phase::Bet,
+            Phase::Draw,
+            Phase::Bet,
+            Phase::Draw,
+            Phase::Bet,
+            Phase::Showdown,
+        ]
+    }
+
+    fn hand_size(&self) -> usize {
+        4
+    }
+
+    fn evaluate(&self, hand: &[Card], _community_cards: &[Card]) -> HandValue {
+        HandValue::Badugi(get_badugi_hand_type(hand))
+    }
+}
+
+/// Looks up a variant by the short name players type when creating a lobby.
+/// 
+/// # Arguments
+/// * `name` - One of `draw`, `deuces`, `joker`, `holdem` or `badugi`.
+/// 
+/// # Returns
+/// * `Some(Arc<dyn PokerVariant>)` - The matching variant.
+/// * `None` - If the name is not recognised.
+pub fn variant_from_name(name: &str) -> Option<Arc<dyn PokerVariant>> {
+    match name {
+        "draw" => Some(Arc::new(FiveCardDraw)),
+        "deuces" => Some(Arc::new(WildFiveCardDraw::deuces_wild())),
+        "joker" => Some(Arc::new(WildFiveCardDraw::with_joker())),
+        "holdem" => Some(Arc::new(TexasHoldem)),
+        "badugi" => Some(Arc::new(Badugi)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::HandCategory;
+
+
+    #[test]
+    fn test_variant_from_name() {
+        assert_eq!(variant_from_name("draw").unwrap().name(), "Five Card Draw");
+        assert_eq!(variant_from_name("holdem").unwrap().name(), "Texas Hold'em");
+        assert_eq!(variant_from_name("badugi").unwrap().name(), "Badugi");
+        assert!(variant_from_name("omaha").is_none());
+    }
+
+    #[test]
+    fn test_wild_five_card_draw() {
+        let hand: Vec<Card> = ["2h", "Kd", "Ks", "Kc", "7h"].iter().map(|card| card.parse().unwrap()).collect();
+        let deuces = variant_from_name("deuces").unwrap();
+        assert_eq!(deuces.jokers(), 0);
+        match deuces.evaluate(&hand, &[]) {
+            HandValue::Poker(rank) => assert_eq!(rank.category, HandCategory::FourOfAKind),
+            HandValue::Badugi(_) => panic!("draw hands are not scored as Badugi"),
+        }
+
+        // in the joker game the same hand is only three of a kind
+        let joker = variant_from_name("joker").unwrap();
+        assert_eq!(joker.jokers(), 1);
+        match joker.evaluate(&hand, &[]) {
+            HandValue::Poker(rank) => assert_eq!(rank.category, HandCategory::ThreeOfAKind),
+            HandValue::Badugi(_) => panic!("draw hands are not scored as Badugi"),
+        }
+    }
+
+    #[test]
+    fn test_variant_phases() {
+        let count = |phases: &[Phase], phase: Phase| phases.iter().filter(|&&p| p == phase).count();
+
+        let holdem = TexasHoldem.phases();
+        assert_eq!(count(&holdem, Phase::Bet), 4);
+        let board: usize = holdem
+            .iter()
+            .map(|phase| match phase {
+                Phase::DealCommunityCards { count, .. } => *count,
+                _ => 0,
+            })
+            .sum();
+        assert_eq!(TexasHoldem.hand_size() + board, 7);
+
+        let badugi = Badugi.phases();
+        assert_eq!(count(&badugi, Phase::Draw), 3);
+        assert_eq!(count(&badugi, Phase::Bet), 4);
+
+        for variant in [variant_from_name("draw"), variant_from_name("holdem"), variant_from_name("badugi")] {
+            let phases = variant.unwrap().phases();
+            assert_eq!(phases.first(), Some(&Phase::ForcedBets));
+            assert_eq!(phases.last(), Some(&Phase::Showdown));
+        }
+
+        assert_eq!(TexasHoldem.forced_bets(), ForcedBets::Blinds { small: 5, big: 10 });
+        assert_eq!(FiveCardDraw.forced_bets(), ForcedBets::Ante(10));
+    }
+
+    #[test]
+    fn test_holdem_uses_community_cards() {
+        // a pair of Aces in the hole plays with the board, giving three of a kind
+        let hand: Vec<Card> = ["Ah", "Ad"].iter().map(|card| card.parse().unwrap()).collect();
+        let board: Vec<Card> = ["As", "6c", "9h", "Jd", "Ks"].iter().map(|card| card.parse().unwrap()).collect();
+        match TexasHoldem.evaluate(&hand, &board) {
+            HandValue::Poker(rank) => assert_eq!(rank.category, HandCategory::ThreeOfAKind),
+            HandValue::Badugi(_) => panic!("Hold'em hands are not scored as Badugi"),
+        }
+    }
+}